@@ -3,8 +3,14 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use marketpalace_capital_call_contract::msg::{HandleMsg, InstantiateMsg, QueryMsg};
-use marketpalace_capital_call_contract::state::State;
+use marketpalace_capital_call_contract::msg::{
+    AuditTrail, CapitalMarker, DetailedStatus, ExecuteResponse, Expiry, HandleMsg, InstantiateMsg,
+    Milestone, ObserverMsg, Parties, Permissions, QueryMsg, ReceiveMsg, RegistryMsg, Summary,
+    Terms, Transfer,
+};
+use marketpalace_capital_call_contract::state::{
+    DocumentRef, State, Status, Subscription, TransitionRecord,
+};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -15,5 +21,23 @@ fn main() {
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(HandleMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(ReceiveMsg), &out_dir);
+    export_schema(&schema_for!(ObserverMsg), &out_dir);
+    export_schema(&schema_for!(RegistryMsg), &out_dir);
     export_schema(&schema_for!(State), &out_dir);
+    export_schema(&schema_for!(Status), &out_dir);
+    export_schema(&schema_for!(Subscription), &out_dir);
+    export_schema(&schema_for!(TransitionRecord), &out_dir);
+    export_schema(&schema_for!(DocumentRef), &out_dir);
+    export_schema(&schema_for!(ExecuteResponse), &out_dir);
+    export_schema(&schema_for!(Permissions), &out_dir);
+    export_schema(&schema_for!(Summary), &out_dir);
+    export_schema(&schema_for!(Terms), &out_dir);
+    export_schema(&schema_for!(CapitalMarker), &out_dir);
+    export_schema(&schema_for!(DetailedStatus), &out_dir);
+    export_schema(&schema_for!(Expiry), &out_dir);
+    export_schema(&schema_for!(Transfer), &out_dir);
+    export_schema(&schema_for!(Milestone), &out_dir);
+    export_schema(&schema_for!(Parties), &out_dir);
+    export_schema(&schema_for!(AuditTrail), &out_dir);
 }
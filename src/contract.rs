@@ -1,15 +1,327 @@
-use cosmwasm_std::StdError;
 use cosmwasm_std::{
-    entry_point, to_binary, BankMsg, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+    entry_point, from_binary, to_binary, Addr, BankMsg, Binary, Coin, ContractResult, CosmosMsg,
+    Deps, DepsMut, Env, MessageInfo, Reply, Response, StdError, StdResult, Storage, SubMsg,
+    Timestamp, Uint128, WasmMsg,
 };
-use provwasm_std::{mint_marker_supply, withdraw_coins, ProvenanceMsg, ProvenanceQuerier};
+use cw2::{set_contract_version, CONTRACT};
+use cw20::{BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg};
+use provwasm_std::{
+    add_json_attribute, mint_marker_supply, transfer_marker_coins, withdraw_coins, MarkerStatus,
+    ProvenanceMsg, ProvenanceQuerier,
+};
+use schemars::JsonSchema;
+use std::fmt;
 
 use crate::error::ContractError;
-use crate::msg::{HandleMsg, InstantiateMsg, MigrateMsg, QueryMsg};
-use crate::state::{config, config_read, State, Status};
+use crate::msg::{
+    Allocation, AuditTrail, CapitalMarker, DetailedStatus, ExecuteResponse, Expiry, HandleMsg,
+    InstantiateMsg, MigrateMsg, Milestone, ObserverMsg, Parties, Permissions, QueryMsg, ReceiveMsg,
+    RegistryMsg, SettlementAttribute, SubscriptionTerms, Summary, Terms, TermsUpdate, Transfer,
+};
+use crate::state::{
+    append_history, commit_keys, commit_keys_read, config, config_raw_key, config_read,
+    config_version, documents, documents_read, history_read, legacy_config_read,
+    pending_transition, pending_transition_read, CommitKey, DocumentRef, FeeBasis, Role, State,
+    Status, Subscription, TransitionRecord, STATE_VERSION,
+};
+
+// cw2 contract info, so explorers and migrations can tell what is deployed
+pub const CONTRACT_NAME: &str = concat!("crates.io:", env!("CARGO_PKG_NAME"));
+pub const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// Reply id of the capital send, the call is only final once it succeeds
+pub const CALL_CAPITAL_REPLY_ID: u64 = 1;
+
+// Reply id of observer and registry notifications, which only reply when they fail
+pub const NOTIFY_REPLY_ID: u64 = 2;
+
+// Cancel reasons are kept in state, so their size is bounded
+const MAX_REASON_LENGTH: usize = 256;
+
+// Ids key deals off chain, so they are bounded like any other stored string
+const MAX_ID_LENGTH: usize = 64;
+
+// Documents are kept in storage, so both their number and uris are bounded
+const MAX_DOCUMENTS: usize = 32;
+const MAX_URI_LENGTH: usize = 256;
+
+// Commit idempotency keys are kept in storage, so both their number for each lp and size are
+// bounded, each lp's oldest being forgotten first
+const MAX_COMMIT_KEYS: usize = 64;
+const MAX_COMMIT_KEY_LENGTH: usize = 64;
+
+// Fees are in basis points of the called capital
+const MAX_FEE_BPS: u16 = 10000;
+
+// Due dates are unix timestamps in seconds, e.g. "1640995200", no later than a timestamp can
+// hold in nanoseconds
+fn parse_due_date(due_date_time: &str) -> Result<Timestamp, ContractError> {
+    due_date_time
+        .parse::<u64>()
+        .ok()
+        .filter(|seconds| *seconds <= u64::MAX / 1_000_000_000)
+        .map(Timestamp::from_seconds)
+        .ok_or_else(|| ContractError::InvalidDueDate {
+            due_date_time: due_date_time.to_string(),
+        })
+}
+
+// timestamps are nanoseconds in a u64, so a time too far out to represent is held at the latest
+// one rather than overflowing
+fn saturating_plus_seconds(time: Timestamp, seconds: u64) -> Timestamp {
+    seconds
+        .checked_mul(1_000_000_000)
+        .and_then(|nanos| time.nanos().checked_add(nanos))
+        .map_or(Timestamp::from_nanos(u64::MAX), Timestamp::from_nanos)
+}
+
+fn validate_coin(coin: &Coin) -> Result<(), ContractError> {
+    if coin.amount.is_zero() || coin.denom.is_empty() {
+        return Err(ContractError::InvalidCoin { coin: coin.clone() });
+    }
+    validate_denom(&coin.denom)
+}
+
+// Denoms follow the cosmos sdk rule [a-zA-Z][a-zA-Z0-9/:._-]{2,127}
+fn validate_denom(denom: &str) -> Result<(), ContractError> {
+    let valid = (3..=128).contains(&denom.len())
+        && denom.starts_with(|c: char| c.is_ascii_alphabetic())
+        && denom
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "/:._-".contains(c));
+    if !valid {
+        return Err(ContractError::InvalidDenom {
+            denom: denom.to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn past_due(state: &State, env: &Env) -> bool {
+    match state.due_date_time {
+        Some(due_date_time) => env.block.time > due_date_time,
+        None => false,
+    }
+}
+
+// formats the due date the way it was given, for errors and attributes
+fn due_date_string(due_date_time: Option<Timestamp>) -> String {
+    due_date_time
+        .map(|due_date_time| due_date_time.seconds().to_string())
+        .unwrap_or_default()
+}
+
+// the admin may only sweep a call this long past its due date
+fn grace_period_end(state: &State) -> Option<Timestamp> {
+    match (state.due_date_time, state.emergency_grace_period) {
+        (Some(due_date_time), Some(grace_period)) => {
+            Some(saturating_plus_seconds(due_date_time, grace_period))
+        }
+        _ => None,
+    }
+}
+
+fn is_finished(state: &State) -> bool {
+    state.status == Status::CapitalCalled || state.status == Status::Cancelled
+}
+
+// once capital is committed the gp can no longer unwind it alone, though anyone can unwind
+// a capital call that was not called before its due date
+fn may_cancel(state: &State, env: &Env, address: &Addr) -> bool {
+    let authorized = if matches!(
+        state.status,
+        Status::CapitalCommitted | Status::ClosePending
+    ) {
+        state.has_role(address, Role::Admin)
+    } else {
+        address == &state.gp || state.has_role(address, Role::Admin)
+    };
+    let authorized = authorized || state.has_role(address, Role::Canceller);
+    authorized || past_due(state, env)
+}
+
+// capital moves by cw20 transfer when it is a cw20 token, otherwise by bank send
+fn send_capital(
+    capital_cw20: Option<&Addr>,
+    recipient: &Addr,
+    capital: Coin,
+) -> StdResult<CosmosMsg<ProvenanceMsg>> {
+    match capital_cw20 {
+        Some(capital_cw20) if capital_cw20.as_str() == capital.denom => Ok(WasmMsg::Execute {
+            contract_addr: capital_cw20.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount: capital.amount,
+            })?,
+            funds: vec![],
+        }
+        .into()),
+        _ => Ok(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![capital],
+        }
+        .into()),
+    }
+}
+
+// the cw20 capital held, which bank balances do not include
+fn held_cw20(deps: Deps, env: &Env, state: &State) -> StdResult<Option<Coin>> {
+    match &state.capital_cw20 {
+        Some(capital_cw20) => {
+            let held: BalanceResponse = deps.querier.query_wasm_smart(
+                capital_cw20,
+                &Cw20QueryMsg::Balance {
+                    address: env.contract.address.to_string(),
+                },
+            )?;
+            Ok(Some(Coin {
+                denom: capital_cw20.to_string(),
+                amount: held.balance,
+            }))
+        }
+        None => Ok(None),
+    }
+}
+
+// what the contract actually holds of a capital denom, bank or cw20
+fn held_capital(deps: Deps, env: &Env, state: &State, denom: &str) -> StdResult<Coin> {
+    match held_cw20(deps, env, state)? {
+        Some(held) if held.denom == denom => Ok(held),
+        _ => deps
+            .querier
+            .query_balance(env.contract.address.clone(), denom),
+    }
+}
+
+// tells every observer the status the call just finished with, an observer that fails never
+// failing the call
+fn notify_observers<T>(state: &State) -> StdResult<Vec<SubMsg<T>>>
+where
+    T: Clone + fmt::Debug + PartialEq + JsonSchema,
+{
+    let msg = to_binary(&ObserverMsg::StatusChanged {
+        id: state.id.clone(),
+        status: state.status.clone(),
+    })?;
+    Ok(state
+        .observers
+        .iter()
+        .map(|observer| {
+            SubMsg::reply_on_error(
+                WasmMsg::Execute {
+                    contract_addr: observer.to_string(),
+                    msg: msg.clone(),
+                    funds: vec![],
+                },
+                NOTIFY_REPLY_ID,
+            )
+        })
+        .collect())
+}
+
+// tells the registry of a status change, if the status changed, again without failing the call
+fn notify_registry<T>(state: &State, prior_status: &Status) -> StdResult<Option<SubMsg<T>>>
+where
+    T: Clone + fmt::Debug + PartialEq + JsonSchema,
+{
+    match &state.registry {
+        Some(registry) if state.status != *prior_status => Ok(Some(SubMsg::reply_on_error(
+            WasmMsg::Execute {
+                contract_addr: registry.to_string(),
+                msg: to_binary(&RegistryMsg::UpdateStatus {
+                    id: state.id.clone(),
+                    status: state.status.clone(),
+                })?,
+                funds: vec![],
+            },
+            NOTIFY_REPLY_ID,
+        ))),
+        _ => Ok(None),
+    }
+}
+
+// the one coin attached, which must be in the expected denom
+fn require_single_coin<'a>(funds: &'a [Coin], expected: &str) -> Result<&'a Coin, ContractError> {
+    match funds {
+        [] => Err(ContractError::NoFundsProvided {}),
+        [coin] if coin.denom == expected => Ok(coin),
+        [coin] => Err(ContractError::WrongDenom {
+            got: coin.denom.clone(),
+            expected: expected.to_string(),
+        }),
+        // anything attached besides the expected coin would be stuck in the contract
+        [_, second, ..] => {
+            let extra = funds
+                .iter()
+                .find(|coin| coin.denom != expected)
+                .unwrap_or(second);
+            Err(ContractError::ExtraFunds {
+                denom: extra.denom.clone(),
+            })
+        }
+    }
+}
+
+// the funds attached besides the commit fee, which must be attached in full
+fn take_commit_fee(commit_fee: Option<&Coin>, funds: &[Coin]) -> Result<Vec<Coin>, ContractError> {
+    let commit_fee = match commit_fee {
+        Some(commit_fee) => commit_fee,
+        None => return Ok(funds.to_vec()),
+    };
+    if !funds.contains(commit_fee) {
+        return Err(ContractError::CommitFeeRequired {
+            fee: commit_fee.clone(),
+        });
+    }
+    Ok(funds
+        .iter()
+        .filter(|coin| coin.denom != commit_fee.denom)
+        .cloned()
+        .collect())
+}
+
+// sends the commit fee on to the fee recipient, both set together at instantiate
+fn add_commit_fee<T>(state: &State, response: Response<T>) -> Response<T>
+where
+    T: Clone + fmt::Debug + PartialEq + JsonSchema,
+{
+    match (&state.commit_fee, &state.fee_recipient) {
+        (Some(commit_fee), Some(fee_recipient)) => response
+            .add_attribute("commit_fee", commit_fee.to_string())
+            .add_attribute("transfer_type", "commit_fee")
+            .add_message(BankMsg::Send {
+                to_address: fee_recipient.to_string(),
+                amount: vec![commit_fee.clone()],
+            }),
+        _ => response,
+    }
+}
+
+// nothing can be committed while a close is proposed, as committing would abort it
+fn check_not_closing(state: &State) -> Result<(), ContractError> {
+    if state.status == Status::ClosePending {
+        return Err(ContractError::WrongStatus {
+            current: state.status.clone(),
+            expected: Status::CapitalCommitted,
+        });
+    }
+    Ok(())
+}
+
+// funds or privileges given to the contract itself could never be used
+fn check_not_contract(env: &Env, address: &Addr) -> Result<(), ContractError> {
+    if address == &env.contract.address {
+        return Err(ContractError::ContractAddress {
+            address: address.clone(),
+        });
+    }
+    Ok(())
+}
 
-fn contract_error(err: &str) -> ContractError {
-    ContractError::Std(StdError::generic_err(err))
+fn may_call(state: &State, address: &Addr) -> bool {
+    address == &state.gp
+        || state.has_role(address, Role::Admin)
+        || state.has_role(address, Role::Closer)
 }
 
 // Note, you can use StdResult in some functions where you do not
@@ -17,351 +329,8435 @@ fn contract_error(err: &str) -> ContractError {
 #[entry_point]
 pub fn instantiate(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
-    let state = State {
-        status: Status::PendingCapital,
+    if msg.id.is_empty() || msg.id.len() > MAX_ID_LENGTH {
+        return Err(ContractError::InvalidId {
+            id: msg.id,
+            max: MAX_ID_LENGTH,
+        });
+    }
+
+    let first = msg
+        .subscriptions
+        .first()
+        .ok_or(ContractError::NoSubscriptions {})?;
+    if first.capital.denom == first.shares.denom {
+        return Err(ContractError::DuplicateDenom {
+            denom: first.capital.denom.clone(),
+        });
+    }
+
+    // funds would loop back to the same party if roles were shared
+    deps.api.addr_validate(msg.admin.as_str())?;
+    check_not_contract(&env, &msg.admin)?;
+    if msg.admin == info.sender {
+        return Err(ContractError::DuplicateRole { address: msg.admin });
+    }
+
+    for (i, terms) in msg.subscriptions.iter().enumerate() {
+        deps.api.addr_validate(terms.lp_capital_source.as_str())?;
+        check_not_contract(&env, &terms.lp_capital_source)?;
+        if let Some(shares_recipient) = &terms.shares_recipient {
+            deps.api.addr_validate(shares_recipient.as_str())?;
+            check_not_contract(&env, shares_recipient)?;
+        }
+        if terms.lp_capital_source == info.sender || terms.lp_capital_source == msg.admin {
+            return Err(ContractError::DuplicateRole {
+                address: terms.lp_capital_source.clone(),
+            });
+        }
+        validate_coin(&terms.capital)?;
+        validate_coin(&terms.shares)?;
+        if terms.capital.denom != first.capital.denom {
+            return Err(ContractError::MixedDenoms {
+                denom: terms.capital.denom.clone(),
+                expected: first.capital.denom.clone(),
+            });
+        }
+        if terms.shares.denom != first.shares.denom {
+            return Err(ContractError::MixedDenoms {
+                denom: terms.shares.denom.clone(),
+                expected: first.shares.denom.clone(),
+            });
+        }
+        if msg.subscriptions[..i]
+            .iter()
+            .any(|other| other.lp_capital_source == terms.lp_capital_source)
+        {
+            return Err(ContractError::DuplicateSubscription {
+                lp_capital_source: terms.lp_capital_source.clone(),
+            });
+        }
+    }
+
+    // an allowlist without the capital denom could never be committed
+    if !msg.allowed_capital_denoms.is_empty()
+        && !msg.allowed_capital_denoms.contains(&first.capital.denom)
+    {
+        return Err(ContractError::DenomNotAllowed {
+            denom: first.capital.denom.clone(),
+        });
+    }
+
+    // cw20 capital is told apart from native coins by its denom
+    if let Some(capital_cw20) = &msg.capital_cw20 {
+        deps.api.addr_validate(capital_cw20.as_str())?;
+        if first.capital.denom != capital_cw20.as_str() {
+            return Err(ContractError::WrongDenom {
+                got: first.capital.denom.clone(),
+                expected: capital_cw20.to_string(),
+            });
+        }
+    }
+
+    if let Some(capital_recipient) = &msg.capital_recipient {
+        deps.api.addr_validate(capital_recipient.as_str())?;
+        check_not_contract(&env, capital_recipient)?;
+    }
+
+    for closer in msg.authorized_closers.iter() {
+        deps.api.addr_validate(closer.as_str())?;
+        check_not_contract(&env, closer)?;
+    }
+
+    for observer in msg.observers.iter().chain(msg.registry.iter()) {
+        deps.api.addr_validate(observer.as_str())?;
+        check_not_contract(&env, observer)?;
+    }
+
+    if let Some(min_commitment) = &msg.min_commitment {
+        validate_coin(min_commitment)?;
+        if min_commitment.denom != first.capital.denom {
+            return Err(ContractError::MixedDenoms {
+                denom: min_commitment.denom.clone(),
+                expected: first.capital.denom.clone(),
+            });
+        }
+    }
+
+    // parsed up front so a malformed due date fails here rather than when it is enforced
+    let due_date_time = msg
+        .due_date_time
+        .as_deref()
+        .map(parse_due_date)
+        .transpose()?;
+
+    if let Some(fee_recipient) = &msg.fee_recipient {
+        deps.api.addr_validate(fee_recipient.as_str())?;
+        check_not_contract(&env, fee_recipient)?;
+    }
+    if let Some(fee_bps) = msg.fee_bps {
+        if fee_bps > MAX_FEE_BPS || msg.fee_recipient.is_none() {
+            return Err(ContractError::InvalidFee { fee_bps });
+        }
+    }
+
+    // kept apart from the capital so a deposit is never mistaken for the fee, and never
+    // combined with cw20 capital whose receive can not carry the fee alongside it
+    if let Some(commit_fee) = &msg.commit_fee {
+        validate_coin(commit_fee)?;
+        if msg.fee_recipient.is_none()
+            || msg.capital_cw20.is_some()
+            || commit_fee.denom == first.capital.denom
+            || commit_fee.denom == first.shares.denom
+        {
+            return Err(ContractError::InvalidCommitFee {
+                fee: commit_fee.clone(),
+            });
+        }
+    }
+
+    // only the deployer instantiates, so only it can skip the commitments
+    let status = msg.initial_status.unwrap_or(Status::PendingCapital);
+    let committed_off_chain = match status {
+        Status::PendingCapital => false,
+        Status::CapitalCommitted => true,
+        current => {
+            return Err(ContractError::WrongStatus {
+                current,
+                expected: Status::CapitalCommitted,
+            })
+        }
+    };
+
+    let mut state = State {
+        version: STATE_VERSION,
+        id: msg.id,
+        status: status.clone(),
         gp: info.sender,
-        lp_capital_source: msg.lp_capital_source,
         admin: msg.admin,
-        capital: msg.capital,
-        shares: msg.shares,
+        subscriptions: msg
+            .subscriptions
+            .into_iter()
+            .map(|terms| Subscription {
+                lp_capital_source: terms.lp_capital_source,
+                status: status.clone(),
+                committed: match committed_off_chain {
+                    true => terms.capital.clone(),
+                    false => Coin::new(0, terms.capital.denom.clone()),
+                },
+                capital: terms.capital,
+                shares: terms.shares,
+                shares_recipient: terms.shares_recipient,
+                committed_from: None,
+                accepted: committed_off_chain,
+                close_confirmed: false,
+            })
+            .collect(),
+        capital_cw20: msg.capital_cw20,
+        capital_recipient: msg.capital_recipient,
+        due_date_time,
+        settlement_attribute: msg.settlement_attribute,
+        allowed_capital_denoms: msg.allowed_capital_denoms,
+        fee_bps: msg.fee_bps,
+        fee_basis: msg.fee_basis.unwrap_or(FeeBasis::Capital),
+        fee_recipient: msg.fee_recipient,
+        commit_fee: msg.commit_fee,
+        authorized_closers: msg.authorized_closers,
+        roles: vec![],
+        observers: msg.observers,
+        registry: msg.registry,
+        auto_refund: msg.auto_refund.unwrap_or(true),
+        emergency_grace_period: msg.emergency_grace_period,
+        min_commit_duration: msg.min_commit_duration,
+        min_commitment: msg.min_commitment,
+        capital_decimals: msg.capital_decimals,
+        shares_decimals: msg.shares_decimals,
+        committed_off_chain,
+        drawn: Uint128::zero(),
+        paused: false,
+        instantiated_at: Some(env.block.time),
+        committed_at: committed_off_chain.then_some(env.block.time),
+        called_at: None,
+        cancelled_at: None,
+        cancel_reason: None,
     };
+
+    // capital the gp attaches commits the first subscription in full, any refund going back to
+    // the gp, while capital committed off chain leaves nothing to attach
+    let mut response = Response::default();
+    if let Some(coin) = info.funds.first() {
+        if committed_off_chain {
+            return Err(ContractError::ExtraFunds {
+                denom: coin.denom.clone(),
+            });
+        }
+        let (accepted, excess) = take_deposit(&state, &state.subscriptions[0], &info.funds)?;
+        let capital = state.subscriptions[0].capital.clone();
+        if accepted.amount < capital.amount {
+            return Err(ContractError::WrongAmount {
+                got: accepted,
+                expected: capital,
+            });
+        }
+        state.subscriptions[0].committed_from = Some(state.gp.clone());
+        let committed = commit_deposit(&mut state, 0, &accepted, &env)?;
+
+        response = response
+            .add_attribute("capital", accepted.to_string())
+            .add_attribute("committed", committed.to_string());
+        if !excess.amount.is_zero() {
+            response = response
+                .add_attribute("transfer_type", "refund")
+                .add_attribute("refund", excess.to_string())
+                .add_message(BankMsg::Send {
+                    to_address: state.gp.to_string(),
+                    amount: vec![excess],
+                });
+        }
+        response = add_commit_fee(&state, response);
+    }
+
     config(deps.storage).save(&state)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
-    Ok(Response::default())
+    if let Some(registry) = &state.registry {
+        response = response.add_message(WasmMsg::Execute {
+            contract_addr: registry.to_string(),
+            msg: to_binary(&RegistryMsg::Register {
+                id: state.id.clone(),
+                contract_address: env.contract.address,
+                status: state.status.clone(),
+            })?,
+            funds: vec![],
+        });
+    }
+
+    Ok(response)
 }
 
 #[entry_point]
-pub fn migrate(_: DepsMut, _: Env, _: MigrateMsg) -> Result<Response, ContractError> {
+pub fn migrate(deps: DepsMut, _: Env, _: MigrateMsg) -> Result<Response, ContractError> {
+    // contracts instantiated before cw2 have no contract info, anything else must be this one
+    if let Some(info) = CONTRACT.may_load(deps.storage)? {
+        if info.contract != CONTRACT_NAME {
+            return Err(ContractError::WrongContract {
+                contract: info.contract,
+            });
+        }
+    }
+
+    let version = config_version(deps.storage)?;
+    if version > STATE_VERSION {
+        return Err(ContractError::UnsupportedVersion {
+            stored: version,
+            supported: STATE_VERSION,
+        });
+    }
+
+    let state: State = if version == 0 {
+        legacy_config_read(deps.storage).load()?.into()
+    } else {
+        config_read(deps.storage).load()?
+    };
+
+    config(deps.storage).save(&State {
+        version: STATE_VERSION,
+        ..state
+    })?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
     Ok(Response::default())
 }
 
 // And declare a custom Error variant for the ones where you will want to make use of it
 #[entry_point]
 pub fn execute(
-    deps: DepsMut,
-    _env: Env,
+    mut deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     msg: HandleMsg,
 ) -> Result<Response<ProvenanceMsg>, ContractError> {
-    match msg {
-        HandleMsg::Cancel {} => try_cancel(deps, _env, info),
-        HandleMsg::CommitCapital {} => try_commit_capital(deps, _env, info),
-        HandleMsg::CallCapital {} => try_call_capital(deps, _env, info),
+    // a call awaiting its reply holds the contract until the reply settles it, so nothing the
+    // settlement sends can execute against the state it left half done
+    if pending_transition_read(deps.storage).may_load()?.is_some() {
+        return Err(ContractError::Reentrancy {});
+    }
+
+    // anything attached to a handler that does not consume it would be stuck in the contract
+    if let Some(coin) = info.funds.first() {
+        if !matches!(
+            msg,
+            HandleMsg::CommitCapital { .. } | HandleMsg::CommitCapitalFrom { .. }
+        ) {
+            return Err(ContractError::UnexpectedFunds {
+                denom: coin.denom.clone(),
+            });
+        }
+    }
+
+    let prior = config_read(deps.storage).load()?;
+    let paused = prior.paused;
+    let sender = info.sender.clone();
+
+    // queries, unpausing, rotating the admin and emergency withdrawals are the only things left
+    // working while paused
+    let response = match msg {
+        HandleMsg::Pause {} => try_set_paused(deps.branch(), env.clone(), info, true),
+        HandleMsg::Unpause {} => try_set_paused(deps.branch(), env.clone(), info, false),
+        HandleMsg::UpdateAdmin { new_admin } => {
+            try_update_admin(deps.branch(), env.clone(), info, new_admin)
+        }
+        HandleMsg::EmergencyWithdraw { recipient } => {
+            try_emergency_withdraw(deps.branch(), env.clone(), info, recipient)
+        }
+        _ if paused => Err(ContractError::Paused {}),
+        HandleMsg::Cancel { reason } => try_cancel(deps.branch(), env.clone(), info, reason),
+        HandleMsg::AcceptTerms {} => try_accept_terms(deps.branch(), env.clone(), info),
+        HandleMsg::CommitCapital { idempotency_key } => {
+            try_commit_capital(deps.branch(), env.clone(), info, idempotency_key)
+        }
+        HandleMsg::Receive(wrapper) => try_receive(deps.branch(), env.clone(), info, wrapper),
+        HandleMsg::CommitCapitalFrom { owner } => {
+            try_commit_capital_from(deps.branch(), env.clone(), info, owner)
+        }
+        HandleMsg::ClaimRefund {} => try_claim_refund(deps.branch(), env.clone(), info),
+        HandleMsg::Reopen {} => try_reopen(deps.branch(), env.clone(), info),
+        HandleMsg::WithdrawExpired {} => try_withdraw_expired(deps.branch(), env.clone(), info),
+        HandleMsg::CallCapital { allocations } => {
+            try_call_capital(deps.branch(), env.clone(), info, allocations)
+        }
+        HandleMsg::DrawCapital {
+            capital_amount,
+            min_capital_out,
+            max_shares_out,
+        } => try_draw_capital(
+            deps.branch(),
+            env.clone(),
+            info,
+            capital_amount,
+            min_capital_out,
+            max_shares_out,
+        ),
+        HandleMsg::ProposeClose {} => try_propose_close(deps.branch(), env.clone(), info),
+        HandleMsg::ConfirmClose {} => try_confirm_close(deps.branch(), env.clone(), info),
+        HandleMsg::AbortClose {} => try_abort_close(deps.branch(), env.clone(), info),
+        HandleMsg::GrantRole { address, role } => {
+            try_grant_role(deps.branch(), env.clone(), info, address, role)
+        }
+        HandleMsg::RevokeRole { address, role } => {
+            try_revoke_role(deps.branch(), env.clone(), info, address, role)
+        }
+        HandleMsg::AttachDocument { hash, uri } => {
+            try_attach_document(deps.branch(), env.clone(), info, hash, uri)
+        }
+        HandleMsg::UpdateTerms { updates } => {
+            try_update_terms(deps.branch(), env.clone(), info, updates)
+        }
+        HandleMsg::UpdateSharesAmount {
+            lp_capital_source,
+            shares,
+        } => try_update_shares_amount(deps.branch(), env.clone(), info, lp_capital_source, shares),
+        HandleMsg::UpdateSubscription {
+            lp_capital_source,
+            new_lp_capital_source,
+        } => try_update_subscription(
+            deps.branch(),
+            env.clone(),
+            info,
+            lp_capital_source,
+            new_lp_capital_source,
+        ),
+        HandleMsg::ExtendDueDate { new_due_date_time } => {
+            try_extend_due_date(deps.branch(), env.clone(), info, new_due_date_time)
+        }
+    }?;
+
+    // the status left behind is returned as data, so callers need not query it
+    let response = response.set_data(execute_data(deps.as_ref())?);
+
+    // every successful execution is recorded with the status it left behind, unless the
+    // handler deferred its transition to a reply which records it instead
+    if pending_transition_read(deps.storage).may_load()?.is_some() {
+        return Ok(response);
     }
+    let state = config_read(deps.storage).load()?;
+    let response = response.add_submessages(notify_registry(&state, &prior.status)?);
+    let status = state.status;
+    append_history(
+        deps.storage,
+        TransitionRecord {
+            status,
+            timestamp: env.block.time,
+            sender,
+        },
+    )?;
+
+    Ok(response)
 }
 
-pub fn try_commit_capital(
+fn execute_data(deps: Deps) -> StdResult<Binary> {
+    let state = config_read(deps.storage).load()?;
+    to_binary(&ExecuteResponse {
+        committed: state.committed()?,
+        status: state.status,
+    })
+}
+
+pub fn try_accept_terms(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
 ) -> Result<Response<ProvenanceMsg>, ContractError> {
-    let state = config_read(deps.storage).load()?;
+    let mut state = config_read(deps.storage).load()?;
 
-    if state.status != Status::PendingCapital {
-        return Err(contract_error("contract no longer pending capital"));
+    if is_finished(&state) {
+        return Err(ContractError::AlreadyFinished {
+            status: state.status,
+        });
     }
 
-    if info.sender != state.lp_capital_source {
-        return Err(contract_error("wrong investor committing capital"));
-    }
+    let sub = match state.subscription_mut(&info.sender) {
+        Some(sub) => sub,
+        None => {
+            return Err(ContractError::Unauthorized {
+                sender: info.sender,
+            })
+        }
+    };
 
-    if info.funds.is_empty() {
-        return Err(contract_error("no capital was committed"));
+    if sub.status != Status::PendingCapital {
+        return Err(ContractError::WrongStatus {
+            current: sub.status.clone(),
+            expected: Status::PendingCapital,
+        });
     }
 
-    let deposit = info.funds.first().unwrap();
-    if deposit != &state.capital {
-        return Err(contract_error("capital does not match required"));
+    // every execution adds to the history, so accepting again is refused rather than recorded
+    if sub.accepted {
+        return Err(ContractError::AlreadyAccepted {
+            lp_capital_source: info.sender,
+        });
     }
 
-    config(deps.storage).update(|mut state| -> Result<_, ContractError> {
-        state.status = Status::CapitalCommitted;
-        Ok(state)
-    })?;
+    sub.accepted = true;
+    config(deps.storage).save(&state)?;
 
-    Ok(Response::default())
+    Ok(Response::new()
+        .add_attribute("action", "accept_terms")
+        .add_attribute("prior_status", state.status.to_string())
+        .add_attribute("status", state.status.to_string())
+        .add_attribute("sender", info.sender))
 }
 
-pub fn try_cancel(
+// a cw20 send is committed as if the lp had attached the tokens themselves
+pub fn try_receive(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
+    wrapper: Cw20ReceiveMsg,
 ) -> Result<Response<ProvenanceMsg>, ContractError> {
     let state = config_read(deps.storage).load()?;
-
-    if state.status == Status::CapitalCalled {
-        return Err(contract_error("capital already called"));
-    } else if state.status == Status::Cancelled {
-        return Err(contract_error("already cancelled"));
+    if state.capital_cw20.as_ref() != Some(&info.sender) {
+        return Err(ContractError::Unauthorized {
+            sender: info.sender,
+        });
     }
 
-    if info.sender != state.gp && info.sender != state.admin {
-        return Err(contract_error("wrong gp cancelling capital call"));
+    let sender = deps.api.addr_validate(&wrapper.sender)?;
+    match from_binary(&wrapper.msg)? {
+        ReceiveMsg::CommitCapital { idempotency_key } => try_commit_capital(
+            deps,
+            env,
+            MessageInfo {
+                sender,
+                funds: vec![Coin {
+                    denom: info.sender.to_string(),
+                    amount: wrapper.amount,
+                }],
+            },
+            idempotency_key,
+        ),
     }
-
-    config(deps.storage).update(|mut state| -> Result<_, ContractError> {
-        state.status = Status::Cancelled;
-        Ok(state)
-    })?;
-
-    Ok(
-        Response::new().add_messages(if state.status == Status::CapitalCommitted {
-            vec![BankMsg::Send {
-                to_address: state.lp_capital_source.to_string(),
-                amount: vec![state.capital],
-            }]
-        } else {
-            vec![]
-        }),
-    )
 }
 
-pub fn try_call_capital(
+pub fn try_commit_capital(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
+    idempotency_key: Option<String>,
 ) -> Result<Response<ProvenanceMsg>, ContractError> {
-    let state = config_read(deps.storage).load()?;
+    let mut state = config_read(deps.storage).load()?;
+    let prior_status = state.status.clone();
 
-    if state.status != Status::CapitalCommitted {
-        return Err(contract_error("capital not committed"));
+    if is_finished(&state) {
+        return Err(ContractError::AlreadyFinished {
+            status: state.status,
+        });
     }
 
-    if info.sender != state.gp && info.sender != state.admin {
-        return Err(contract_error("wrong gp calling capital"));
-    }
+    check_not_closing(&state)?;
 
-    config(deps.storage).update(|mut state| -> Result<_, ContractError> {
-        state.status = Status::CapitalCalled;
-        Ok(state)
-    })?;
+    let index = match state
+        .subscriptions
+        .iter()
+        .position(|sub| sub.lp_capital_source == info.sender)
+    {
+        Some(index) => index,
+        None => {
+            return Err(ContractError::Unauthorized {
+                sender: info.sender,
+            })
+        }
+    };
+    let sub = &state.subscriptions[index];
 
-    let mint = mint_marker_supply(state.shares.amount.into(), state.shares.denom.clone())?;
-    let withdraw = withdraw_coins(
-        state.shares.denom.clone(),
-        state.shares.amount.into(),
-        state.shares.denom.clone(),
-        state.lp_capital_source,
-    )?;
+    if sub.status != Status::PendingCapital {
+        return Err(ContractError::WrongStatus {
+            current: sub.status.clone(),
+            expected: Status::PendingCapital,
+        });
+    }
 
-    let marker = ProvenanceQuerier::new(&deps.querier).get_marker_by_denom(state.shares.denom)?;
+    if !sub.accepted {
+        return Err(ContractError::TermsNotAccepted {
+            lp_capital_source: info.sender,
+        });
+    }
 
-    Ok(Response::new().add_messages(vec![
-        mint,
-        withdraw,
-        BankMsg::Send {
-            to_address: marker.address.to_string(),
-            amount: vec![state.capital],
-        }
-        .into(),
-    ]))
-}
+    let (accepted, excess) = take_deposit(&state, sub, &info.funds)?;
+    let committed = commit_deposit(&mut state, index, &accepted, &env)?;
+    if let Some(key) = &idempotency_key {
+        record_commit_key(deps.storage, &info.sender, key)?;
+    }
+    config(deps.storage).save(&state)?;
 
-#[entry_point]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::GetStatus {} => to_binary(&query_status(deps)?),
+    let mut response = Response::new()
+        .add_attribute("action", "commit_capital")
+        .add_attribute("prior_status", prior_status.to_string())
+        .add_attribute("status", state.status.to_string())
+        .add_attribute("sender", info.sender.clone())
+        .add_attribute("capital", accepted.to_string())
+        .add_attribute("committed", committed.to_string());
+    if !excess.amount.is_zero() {
+        response = response
+            .add_attribute("transfer_type", "refund")
+            .add_attribute("refund", excess.to_string())
+            .add_message(send_capital(
+                state.capital_cw20.as_ref(),
+                &info.sender,
+                excess,
+            )?);
     }
-}
 
-fn query_status(deps: Deps) -> StdResult<Status> {
-    let state = config_read(deps.storage).load()?;
-    Ok(state.status)
+    Ok(add_commit_fee(&state, response))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use cosmwasm_std::testing::{mock_env, mock_info};
-    use cosmwasm_std::{coins, from_binary, Addr, Coin, CosmosMsg};
-    use provwasm_mocks::{mock_dependencies, must_read_binary_file};
-    use provwasm_std::{Marker, MarkerMsgParams, ProvenanceMsgParams};
+// the part of a deposit the subscription still requires and any excess to refund, checked the
+// same way wherever capital is attached
+fn take_deposit(
+    state: &State,
+    sub: &Subscription,
+    funds: &[Coin],
+) -> Result<(Coin, Coin), ContractError> {
+    let funds = take_commit_fee(state.commit_fee.as_ref(), funds)?;
+    let remaining = Coin {
+        denom: sub.capital.denom.clone(),
+        amount: sub.capital.amount.checked_sub(sub.committed.amount)?,
+    };
 
-    fn inst_msg() -> InstantiateMsg {
-        InstantiateMsg {
-            lp_capital_source: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
-            admin: Addr::unchecked("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000"),
-            capital: Coin::new(1000000, "cfigure"),
-            shares: Coin::new(10, "fund-coin"),
+    // the allowlist is checked before the capital is matched
+    if let [coin] = funds.as_slice() {
+        if !state.allows_capital_denom(&coin.denom) {
+            return Err(ContractError::DenomNotAllowed {
+                denom: coin.denom.clone(),
+            });
         }
     }
+    let deposit = require_single_coin(&funds, &remaining.denom)?;
 
-    #[test]
-    fn initialization() {
-        let mut deps = mock_dependencies(&[]);
-        let info = mock_info("creator", &[]);
+    // dust deposits are turned away, though the one completing the capital may be smaller
+    if let Some(min_commitment) = &state.min_commitment {
+        if deposit.amount < min_commitment.amount && deposit.amount < remaining.amount {
+            return Err(ContractError::BelowMinimum {
+                deposit: deposit.clone(),
+                min_commitment: min_commitment.clone(),
+            });
+        }
+    }
 
-        // we can just call .unwrap() to assert this was a success
-        let res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
-        assert_eq!(0, res.messages.len());
+    // anything beyond the capital still required goes straight back to the depositor
+    let accepted = Coin {
+        denom: deposit.denom.clone(),
+        amount: deposit.amount.min(remaining.amount),
+    };
+    let excess = Coin {
+        denom: deposit.denom.clone(),
+        amount: deposit.amount.checked_sub(accepted.amount)?,
+    };
+    Ok((accepted, excess))
+}
 
-        // it worked, let's query the state
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
-        let status: Status = from_binary(&res).unwrap();
-        assert_eq!(Status::PendingCapital, status);
+// adds the accepted deposit to a subscription, returning its running total
+fn commit_deposit(
+    state: &mut State,
+    index: usize,
+    accepted: &Coin,
+    env: &Env,
+) -> Result<Coin, ContractError> {
+    // a subscription is only committed once its running total reaches the requirement
+    let sub = &mut state.subscriptions[index];
+    sub.committed.amount = sub.committed.amount.checked_add(accepted.amount)?;
+    if sub.committed.amount == sub.capital.amount {
+        sub.status = Status::CapitalCommitted;
     }
+    let committed = sub.committed.clone();
 
-    #[test]
-    fn commit_capital() {
-        let mut deps = mock_dependencies(&coins(2, "token"));
+    state.transition(state.pending_status())?;
+    if state.status == Status::CapitalCommitted && state.committed_at.is_none() {
+        state.committed_at = Some(env.block.time);
+    }
+    Ok(committed)
+}
 
-        let info = mock_info("creator", &[]);
-        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+// remembers the key the lp commits with, failing the commit if it was already used
+fn record_commit_key(
+    storage: &mut dyn Storage,
+    lp_capital_source: &Addr,
+    key: &str,
+) -> Result<(), ContractError> {
+    if key.is_empty() || key.len() > MAX_COMMIT_KEY_LENGTH {
+        return Err(ContractError::InvalidIdempotencyKey {
+            key: key.to_string(),
+            max: MAX_COMMIT_KEY_LENGTH,
+        });
+    }
 
-        // lp can commit capital
-        let info = mock_info(
-            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
-            &coins(1000000, "cfigure"),
-        );
-        let msg = HandleMsg::CommitCapital {};
-        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let mut keys = commit_keys_read(storage).may_load()?.unwrap_or_default();
+    if keys
+        .iter()
+        .any(|seen| &seen.lp_capital_source == lp_capital_source && seen.key == key)
+    {
+        return Err(ContractError::DuplicateCommit {
+            key: key.to_string(),
+        });
+    }
 
-        // should be in capital commited state
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
-        let status: Status = from_binary(&res).unwrap();
-        assert_eq!(Status::CapitalCommitted, status);
+    // other lps' keys never push out this lp's
+    let lp_keys = keys
+        .iter()
+        .filter(|seen| &seen.lp_capital_source == lp_capital_source)
+        .count();
+    if lp_keys >= MAX_COMMIT_KEYS {
+        if let Some(oldest) = keys
+            .iter()
+            .position(|seen| &seen.lp_capital_source == lp_capital_source)
+        {
+            keys.remove(oldest);
+        }
+    }
+    keys.push(CommitKey {
+        lp_capital_source: lp_capital_source.clone(),
+        key: key.to_string(),
+    });
+    commit_keys(storage).save(&keys)?;
+    Ok(())
+}
+
+pub fn try_commit_capital_from(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    owner: Addr,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    let mut state = config_read(deps.storage).load()?;
+    let prior_status = state.status.clone();
+
+    if is_finished(&state) {
+        return Err(ContractError::AlreadyFinished {
+            status: state.status,
+        });
+    }
+
+    check_not_closing(&state)?;
+
+    // a marker transfer can not pull cw20 tokens, which the lp sends instead
+    if let Some(capital_cw20) = &state.capital_cw20 {
+        return Err(ContractError::Cw20Capital {
+            token: capital_cw20.clone(),
+        });
+    }
+
+    // checked up front as the subscription below borrows the state mutably
+    let commit_fee = state.commit_fee.clone();
+
+    let sub = match state.subscription_mut(&info.sender) {
+        Some(sub) => sub,
+        None => {
+            return Err(ContractError::Unauthorized {
+                sender: info.sender,
+            })
+        }
+    };
+
+    if sub.status != Status::PendingCapital {
+        return Err(ContractError::WrongStatus {
+            current: sub.status.clone(),
+            expected: Status::PendingCapital,
+        });
+    }
+
+    if !sub.accepted {
+        return Err(ContractError::TermsNotAccepted {
+            lp_capital_source: info.sender,
+        });
+    }
+
+    // the capital is pulled rather than attached
+    let funds = take_commit_fee(commit_fee.as_ref(), &info.funds)?;
+    if let Some(coin) = funds.first() {
+        return Err(ContractError::ExtraFunds {
+            denom: coin.denom.clone(),
+        });
+    }
+
+    // refunds go back to a single source, so the whole capital comes from the owner
+    if !sub.committed.amount.is_zero() {
+        return Err(ContractError::PartiallyCommitted {
+            committed: sub.committed.clone(),
+        });
+    }
+
+    deps.api.addr_validate(owner.as_str())?;
+
+    // the marker transfer fails the whole commitment unless the owner allowed it
+    let transfer = transfer_marker_coins(
+        sub.capital.amount.u128(),
+        sub.capital.denom.clone(),
+        env.contract.address.clone(),
+        owner.clone(),
+    )?;
+    sub.committed = sub.capital.clone();
+    sub.committed_from = Some(owner.clone());
+    sub.status = Status::CapitalCommitted;
+    let committed = sub.committed.clone();
+
+    state.transition(state.pending_status())?;
+    if state.status == Status::CapitalCommitted && state.committed_at.is_none() {
+        state.committed_at = Some(env.block.time);
+    }
+    config(deps.storage).save(&state)?;
+
+    let response = Response::new()
+        .add_message(transfer)
+        .add_attribute("action", "commit_capital_from")
+        .add_attribute("prior_status", prior_status.to_string())
+        .add_attribute("status", state.status.to_string())
+        .add_attribute("sender", info.sender)
+        .add_attribute("owner", owner)
+        .add_attribute("capital", committed.to_string())
+        .add_attribute("committed", committed.to_string());
+
+    Ok(add_commit_fee(&state, response))
+}
+
+pub fn try_cancel(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    reason: Option<String>,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    let mut state = config_read(deps.storage).load()?;
+    let prior_status = state.status.clone();
+
+    if is_finished(&state) {
+        return Err(ContractError::AlreadyFinished {
+            status: state.status,
+        });
+    }
+
+    if !may_cancel(&state, &env, &info.sender) {
+        return Err(ContractError::Unauthorized {
+            sender: info.sender,
+        });
+    }
+
+    if let Some(reason) = &reason {
+        if reason.len() > MAX_REASON_LENGTH {
+            return Err(ContractError::ReasonTooLong {
+                length: reason.len(),
+                max: MAX_REASON_LENGTH,
+            });
+        }
+    }
+
+    let mut response = Response::new()
+        .add_attribute("action", "cancel")
+        .add_attribute("prior_status", prior_status.to_string())
+        .add_attribute("status", Status::Cancelled.to_string())
+        .add_attribute("sender", info.sender);
+    if let Some(reason) = &reason {
+        response = response.add_attribute("reason", reason);
+    }
+    let response = unwind(deps.as_ref(), &mut state, &env, response)?;
+    state.cancel_reason = reason;
+    config(deps.storage).save(&state)?;
+
+    Ok(response)
+}
+
+pub fn try_claim_refund(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    let mut state = config_read(deps.storage).load()?;
+    let held = match state.subscriptions.first() {
+        Some(sub) => held_capital(deps.as_ref(), &env, &state, &sub.capital.denom)?,
+        None => return Err(ContractError::NoSubscriptions {}),
+    };
+
+    if state.status != Status::Cancelled {
+        return Err(ContractError::WrongStatus {
+            current: state.status,
+            expected: Status::Cancelled,
+        });
+    }
+
+    let sub = match state.subscription_mut(&info.sender) {
+        Some(sub) => sub,
+        None => {
+            return Err(ContractError::Unauthorized {
+                sender: info.sender,
+            })
+        }
+    };
+
+    if sub.committed.amount.is_zero() {
+        return Err(ContractError::NoRefund {});
+    }
+
+    // as when cancelling, never more than is held, leaving the rest to claim later
+    if held.amount.is_zero() {
+        return Err(ContractError::InsufficientFunds {
+            held,
+            required: sub.committed.clone(),
+        });
+    }
+    let refund = Coin {
+        denom: sub.committed.denom.clone(),
+        amount: sub.committed.amount.min(held.amount),
+    };
+    let recipient = sub.refund_recipient().clone();
+    sub.committed.amount = sub.committed.amount.checked_sub(refund.amount)?;
+    config(deps.storage).save(&state)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "claim_refund")
+        .add_attribute("prior_status", state.status.to_string())
+        .add_attribute("status", state.status.to_string())
+        .add_attribute("sender", info.sender.clone())
+        .add_attribute("transfer_type", "refund")
+        .add_attribute("refund", refund.to_string())
+        .add_message(send_capital(
+            state.capital_cw20.as_ref(),
+            &recipient,
+            refund,
+        )?))
+}
+
+pub fn try_reopen(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    let mut state = config_read(deps.storage).load()?;
+    let prior_status = state.status.clone();
+
+    if !state.has_role(&info.sender, Role::Admin) {
+        return Err(ContractError::Unauthorized {
+            sender: info.sender,
+        });
+    }
+
+    if state.status != Status::Cancelled {
+        return Err(ContractError::WrongStatus {
+            current: state.status,
+            expected: Status::Cancelled,
+        });
+    }
+
+    // shares issued for capital already drawn cannot be taken back
+    if !state.drawn.is_zero() {
+        return Err(ContractError::PartiallyDrawn {
+            drawn: Coin {
+                denom: state.subscriptions[0].capital.denom.clone(),
+                amount: state.drawn,
+            },
+        });
+    }
+
+    // capital still waiting to be claimed would be lost when every lp starts over
+    let committed = state.committed()?;
+    if !committed.amount.is_zero() {
+        return Err(ContractError::RefundsOutstanding { committed });
+    }
+
+    for sub in state.subscriptions.iter_mut() {
+        sub.status = Status::PendingCapital;
+        sub.committed.amount = Uint128::zero();
+        sub.committed_from = None;
+    }
+    state.transition(Status::PendingCapital)?;
+    state.committed_off_chain = false;
+    state.committed_at = None;
+    state.cancelled_at = None;
+    state.cancel_reason = None;
+    config(deps.storage).save(&state)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "reopen")
+        .add_attribute("prior_status", prior_status.to_string())
+        .add_attribute("status", state.status.to_string())
+        .add_attribute("sender", info.sender))
+}
+
+pub fn try_withdraw_expired(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    let mut state = config_read(deps.storage).load()?;
+    let prior_status = state.status.clone();
+
+    if is_finished(&state) {
+        return Err(ContractError::AlreadyFinished {
+            status: state.status,
+        });
+    }
+
+    // a close proposed but never confirmed unwinds all the same
+    if !matches!(
+        state.status,
+        Status::CapitalCommitted | Status::ClosePending
+    ) {
+        return Err(ContractError::WrongStatus {
+            current: state.status,
+            expected: Status::CapitalCommitted,
+        });
+    }
+
+    if state.subscription(&info.sender).is_none() {
+        return Err(ContractError::Unauthorized {
+            sender: info.sender,
+        });
+    }
+
+    if !past_due(&state, &env) {
+        return Err(ContractError::NotPastDue {
+            due_date_time: due_date_string(state.due_date_time),
+        });
+    }
+
+    let response = Response::new()
+        .add_attribute("action", "withdraw_expired")
+        .add_attribute("prior_status", prior_status.to_string())
+        .add_attribute("status", Status::Cancelled.to_string())
+        .add_attribute("sender", info.sender);
+    let response = unwind(deps.as_ref(), &mut state, &env, response)?;
+    config(deps.storage).save(&state)?;
+
+    Ok(response)
+}
+
+// any capital committed so far, partial or not, goes back to its lp, or is kept for it to
+// claim without auto_refund
+fn unwind(
+    deps: Deps,
+    state: &mut State,
+    env: &Env,
+    mut response: Response<ProvenanceMsg>,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // subscriptions share one capital denom, and refunds never send more than is held
+    let mut held = match state.subscriptions.first() {
+        Some(sub) if state.auto_refund && !state.committed_off_chain => {
+            held_capital(deps, env, state, &sub.capital.denom)?.amount
+        }
+        _ => Uint128::zero(),
+    };
+    // capital already drawn has gone to the gp, so only what is left undrawn can be returned
+    let mut total = Uint128::zero();
+    for sub in state.subscriptions.iter() {
+        if sub.status == Status::CapitalCommitted {
+            total = total.checked_add(sub.capital.amount)?;
+        }
+    }
+    let drawn = state.drawn;
+    state.transition(Status::Cancelled)?;
+    for sub in state.subscriptions.iter_mut() {
+        if !drawn.is_zero() && sub.status == Status::CapitalCommitted {
+            sub.committed.amount = sub
+                .committed
+                .amount
+                .checked_sub(sub.capital.amount.multiply_ratio(drawn, total))?;
+        }
+        // capital committed off chain was never held, so there is nothing to return
+        if state.committed_off_chain {
+            sub.committed.amount = Uint128::zero();
+        } else if state.auto_refund && !sub.committed.amount.is_zero() {
+            let refund = Coin {
+                denom: sub.committed.denom.clone(),
+                amount: sub.committed.amount.min(held),
+            };
+            // whatever could not be sent stays committed for the lp to claim later
+            held = held.checked_sub(refund.amount)?;
+            sub.committed.amount = sub.committed.amount.checked_sub(refund.amount)?;
+            if !refund.amount.is_zero() {
+                response = response
+                    .add_attribute("transfer_type", "refund")
+                    .add_attribute("refund", refund.to_string())
+                    .add_message(send_capital(
+                        state.capital_cw20.as_ref(),
+                        sub.refund_recipient(),
+                        refund,
+                    )?);
+            }
+        }
+        sub.status = Status::Cancelled;
+    }
+    state.cancelled_at = Some(env.block.time);
+    Ok(response.add_submessages(notify_observers(state)?))
+}
+
+// capital can only be called once, after it is committed
+fn check_callable(state: &State) -> Result<(), ContractError> {
+    // retries need to tell a finished call apart from one that is not ready
+    if is_finished(state) {
+        return Err(ContractError::AlreadyFinished {
+            status: state.status.clone(),
+        });
+    }
+
+    if state.status != Status::CapitalCommitted {
+        return Err(ContractError::WrongStatus {
+            current: state.status.clone(),
+            expected: Status::CapitalCommitted,
+        });
+    }
+
+    Ok(())
+}
+
+// transfer type, recipient and amount of a capital send
+type CapitalSend = (&'static str, Addr, Coin);
+
+// Outcome of calling capital, shared by CallCapital, DrawCapital and SimulateCall
+struct Settlement {
+    capital: Coin,
+    shares: Coin,
+    fee: Option<Coin>,
+    // fee recipient and the shares taken from the lps when the fee is on the shares
+    shares_fee: Option<(Addr, Coin)>,
+    // lp, shares issued and capital drawn for each subscription called
+    called: Vec<(Addr, Coin, Coin)>,
+    // capital sends in the order they go out, the last is confirmed in reply, none when the
+    // capital was committed off chain
+    sends: Vec<CapitalSend>,
+    refunds: Vec<(Addr, Coin)>,
+    // whether this settles the call, rather than drawing part of it
+    complete: bool,
+}
+
+// settles the subscriptions in state, leaving the overall status for the caller
+fn settle(
+    deps: Deps,
+    state: &mut State,
+    allocations: &[Allocation],
+) -> Result<Settlement, ContractError> {
+    // the capital already drawn was settled at the agreed price, so the rest must be too
+    if !state.drawn.is_zero() {
+        return Err(ContractError::PartiallyDrawn {
+            drawn: Coin {
+                denom: state.subscriptions[0].capital.denom.clone(),
+                amount: state.drawn,
+            },
+        });
+    }
+
+    // the gp may issue fewer shares than agreed, drawing only the matching capital
+    for allocation in allocations.iter() {
+        let sub = state
+            .subscription(&allocation.lp_capital_source)
+            .ok_or_else(|| ContractError::NotSubscribed {
+                address: allocation.lp_capital_source.clone(),
+            })?;
+        if sub.status != Status::CapitalCommitted {
+            return Err(ContractError::WrongStatus {
+                current: sub.status.clone(),
+                expected: Status::CapitalCommitted,
+            });
+        }
+        if allocation.shares.is_zero() || allocation.shares > sub.shares.amount {
+            return Err(ContractError::WrongShares {
+                got: Coin {
+                    denom: sub.shares.denom.clone(),
+                    amount: allocation.shares,
+                },
+                expected: sub.shares.clone(),
+            });
+        }
+    }
+
+    let mut capital = Coin::new(0, state.subscriptions[0].capital.denom.clone());
+    let mut shares = Coin::new(0, state.subscriptions[0].shares.denom.clone());
+    let mut called = vec![];
+    let mut refunds = vec![];
+    let committed_off_chain = state.committed_off_chain;
+    for sub in state.subscriptions.iter_mut() {
+        // partial commitments are never called, only refunded
+        let (called_capital, called_shares) = if sub.status == Status::CapitalCommitted {
+            let called_shares = allocations
+                .iter()
+                .find(|allocation| allocation.lp_capital_source == sub.lp_capital_source)
+                .map_or(sub.shares.amount, |allocation| allocation.shares);
+
+            // rounds down so the lp never pays more than its share of the capital
+            let called_capital = sub
+                .capital
+                .amount
+                .multiply_ratio(called_shares, sub.shares.amount);
+
+            called.push((
+                sub.lp_capital_source.clone(),
+                Coin {
+                    denom: sub.shares.denom.clone(),
+                    amount: called_shares,
+                },
+                Coin {
+                    denom: sub.capital.denom.clone(),
+                    amount: called_capital,
+                },
+            ));
+            sub.status = Status::CapitalCalled;
+            (called_capital, called_shares)
+        } else {
+            sub.status = Status::Cancelled;
+            (Uint128::zero(), Uint128::zero())
+        };
+
+        // capital committed off chain was never held, so none of it is refunded
+        let refund = sub.committed.amount.checked_sub(called_capital)?;
+        if !refund.is_zero() && !committed_off_chain {
+            refunds.push((
+                sub.refund_recipient().clone(),
+                Coin {
+                    denom: sub.committed.denom.clone(),
+                    amount: refund,
+                },
+            ));
+        }
+
+        sub.committed.amount = called_capital;
+        capital.amount = capital.amount.checked_add(called_capital)?;
+        shares.amount = shares.amount.checked_add(called_shares)?;
+    }
+
+    let shares_fee = take_shares_fee(state, &mut called)?;
+    let (fee, sends) = capital_sends(deps, state, &capital, &shares.denom)?;
+    Ok(Settlement {
+        capital,
+        shares,
+        fee: fee.or_else(|| shares_fee.as_ref().map(|(_, fee)| fee.clone())),
+        shares_fee,
+        called,
+        sends,
+        refunds,
+        complete: true,
+    })
+}
+
+// draws part of the committed capital at the agreed price, settling the call once all of it is
+fn draw(
+    deps: Deps,
+    state: &mut State,
+    capital_amount: Uint128,
+) -> Result<Settlement, ContractError> {
+    let mut capital = Coin::new(0, state.subscriptions[0].capital.denom.clone());
+    let mut shares = Coin::new(0, state.subscriptions[0].shares.denom.clone());
+
+    let mut total = Uint128::zero();
+    for sub in state.subscriptions.iter() {
+        if sub.status == Status::CapitalCommitted {
+            total = total.checked_add(sub.capital.amount)?;
+        }
+    }
+    let prior_drawn = state.drawn;
+    let drawn = prior_drawn.checked_add(capital_amount)?;
+    validate_coin(&Coin {
+        denom: capital.denom.clone(),
+        amount: capital_amount,
+    })?;
+    if drawn > total {
+        return Err(ContractError::DrawExceedsCommitted {
+            capital: Coin {
+                denom: capital.denom.clone(),
+                amount: capital_amount,
+            },
+            remaining: Coin {
+                denom: capital.denom.clone(),
+                amount: total.checked_sub(prior_drawn)?,
+            },
+        });
+    }
+    let complete = drawn == total;
+
+    let mut called = vec![];
+    let mut refunds = vec![];
+    for sub in state.subscriptions.iter_mut() {
+        // as with a full call, partial commitments are refunded by the first draw
+        if sub.status == Status::PendingCapital {
+            if !sub.committed.amount.is_zero() {
+                refunds.push((sub.refund_recipient().clone(), sub.committed.clone()));
+                sub.committed.amount = Uint128::zero();
+            }
+            sub.status = Status::Cancelled;
+        }
+        if sub.status != Status::CapitalCommitted {
+            continue;
+        }
+
+        // each lp pays its share of everything drawn so far less what it already paid, so the
+        // last draw settles every lp in full despite rounding down
+        let drawn_capital = sub
+            .capital
+            .amount
+            .multiply_ratio(drawn, total)
+            .checked_sub(sub.capital.amount.multiply_ratio(prior_drawn, total))?;
+        let drawn_shares = sub
+            .shares
+            .amount
+            .multiply_ratio(drawn, total)
+            .checked_sub(sub.shares.amount.multiply_ratio(prior_drawn, total))?;
+
+        if complete {
+            sub.status = Status::CapitalCalled;
+        }
+
+        if !drawn_shares.is_zero() {
+            called.push((
+                sub.lp_capital_source.clone(),
+                Coin {
+                    denom: sub.shares.denom.clone(),
+                    amount: drawn_shares,
+                },
+                Coin {
+                    denom: sub.capital.denom.clone(),
+                    amount: drawn_capital,
+                },
+            ));
+        }
+        capital.amount = capital.amount.checked_add(drawn_capital)?;
+        shares.amount = shares.amount.checked_add(drawn_shares)?;
+    }
+    state.drawn = drawn;
+
+    let shares_fee = take_shares_fee(state, &mut called)?;
+    let (fee, mut sends) = capital_sends(deps, state, &capital, &shares.denom)?;
+    // only the send that settles the call needs to go out regardless, to be confirmed in reply
+    if !complete {
+        sends.retain(|(_, _, coin)| !coin.amount.is_zero());
+    }
+    Ok(Settlement {
+        capital,
+        shares,
+        fee: fee.or_else(|| shares_fee.as_ref().map(|(_, fee)| fee.clone())),
+        shares_fee,
+        called,
+        sends,
+        refunds,
+        complete,
+    })
+}
+
+// the fee and the rest of the called capital, in the order they are sent
+// called capital goes to the shares marker unless configured otherwise
+fn capital_recipient(deps: Deps, state: &State, shares_denom: &str) -> StdResult<Addr> {
+    match &state.capital_recipient {
+        Some(capital_recipient) => Ok(capital_recipient.clone()),
+        None => Ok(ProvenanceQuerier::new(&deps.querier)
+            .get_marker_by_denom(shares_denom)?
+            .address),
+    }
+}
+
+// takes the fee from each lp's called shares, rounding down for each, when the fee is on the
+// shares
+fn take_shares_fee(
+    state: &State,
+    called: &mut [(Addr, Coin, Coin)],
+) -> Result<Option<(Addr, Coin)>, ContractError> {
+    let (fee_bps, fee_recipient) = match (state.fee_bps, &state.fee_recipient, &state.fee_basis) {
+        (Some(fee_bps), Some(fee_recipient), FeeBasis::Shares) => (fee_bps, fee_recipient),
+        _ => return Ok(None),
+    };
+    let mut fee = Coin::new(0, state.subscriptions[0].shares.denom.clone());
+    for (_, shares, _) in called.iter_mut() {
+        let lp_fee = shares.amount.multiply_ratio(fee_bps, MAX_FEE_BPS);
+        shares.amount = shares.amount.checked_sub(lp_fee)?;
+        fee.amount = fee.amount.checked_add(lp_fee)?;
+    }
+    Ok(Some((fee_recipient.clone(), fee)))
+}
+
+fn capital_sends(
+    deps: Deps,
+    state: &State,
+    capital: &Coin,
+    shares_denom: &str,
+) -> Result<(Option<Coin>, Vec<CapitalSend>), ContractError> {
+    // capital committed off chain was never held, so there is nothing to send
+    if state.committed_off_chain {
+        return Ok((None, vec![]));
+    }
+
+    let capital_recipient = capital_recipient(deps, state, shares_denom)?;
+
+    // the fee rounds down and is bounded at instantiate, so it never exceeds the capital
+    let fee = match (state.fee_bps, &state.fee_recipient, &state.fee_basis) {
+        (Some(fee_bps), Some(fee_recipient), FeeBasis::Capital) => Some((
+            fee_recipient.clone(),
+            Coin {
+                denom: capital.denom.clone(),
+                amount: capital.amount.multiply_ratio(fee_bps, MAX_FEE_BPS),
+            },
+        )),
+        _ => None,
+    };
+    let remainder = Coin {
+        denom: capital.denom.clone(),
+        amount: capital
+            .amount
+            .checked_sub(fee.as_ref().map_or(Uint128::zero(), |(_, fee)| fee.amount))?,
+    };
+
+    // the fee is sent alone when it takes all the capital
+    let mut sends = vec![];
+    if let Some((fee_recipient, fee)) = fee.as_ref().filter(|(_, fee)| !fee.amount.is_zero()) {
+        sends.push(("fee", fee_recipient.clone(), fee.clone()));
+    }
+    if !remainder.amount.is_zero() || sends.is_empty() {
+        sends.push(("settlement", capital_recipient, remainder));
+    }
+
+    Ok((fee.map(|(_, fee)| fee), sends))
+}
+
+// the call is pending in reply once the settlement completes it, so the status only changes
+// when its capital send is confirmed
+fn pay_out(
+    deps: DepsMut,
+    env: &Env,
+    sender: Addr,
+    action: &str,
+    mut state: State,
+    prior_status: Status,
+    settlement: Settlement,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    if !state.committed_off_chain {
+        check_held(deps.as_ref(), env, &state, &settlement)?;
+    }
+
+    // capital committed off chain has no send to confirm, so its call settles at once
+    let settled = settlement.complete && settlement.sends.is_empty();
+    if settled {
+        state.transition(Status::CapitalCalled)?;
+        state.called_at = Some(env.block.time);
+    }
+    config(deps.storage).save(&state)?;
+    let status = match settlement.complete && !settled {
+        true => {
+            pending_transition(deps.storage).save(&TransitionRecord {
+                status: Status::CapitalCalled,
+                timestamp: env.block.time,
+                sender: sender.clone(),
+            })?;
+            Status::CapitalCalled
+        }
+        false => state.status.clone(),
+    };
+
+    let mut response = Response::new()
+        .add_submessages(settlement_messages(&state, &settlement)?)
+        .add_attribute("action", action)
+        .add_attribute("prior_status", prior_status.to_string())
+        .add_attribute("status", status.to_string())
+        .add_attribute("sender", sender)
+        .add_attribute("capital", settlement.capital.to_string())
+        .add_attribute("shares", settlement.shares.to_string());
+    if settled {
+        response = response.add_submessages(notify_observers(&state)?);
+    }
+    if let Some(fee) = settlement.fee {
+        response = response.add_attribute("fee", fee.to_string());
+    }
+
+    for (_, refund) in settlement.refunds.iter() {
+        response = response.add_attribute("refund", refund.to_string());
+    }
+
+    // each send is tagged with what it is for, in the order the sends go out
+    let refunds = settlement.refunds.iter().map(|_| "refund");
+    let sends = settlement
+        .sends
+        .iter()
+        .map(|(transfer_type, _, _)| *transfer_type);
+    for transfer_type in refunds.chain(sends) {
+        response = response.add_attribute("transfer_type", transfer_type);
+    }
+
+    Ok(response)
+}
+
+// every send and refund is paid from capital the contract holds, which the recorded commitments
+// should never exceed
+fn check_held(
+    deps: Deps,
+    env: &Env,
+    state: &State,
+    settlement: &Settlement,
+) -> Result<(), ContractError> {
+    let mut required = Coin::new(0, settlement.capital.denom.clone());
+    for (_, _, coin) in settlement.sends.iter() {
+        required.amount = required.amount.checked_add(coin.amount)?;
+    }
+    for (_, refund) in settlement.refunds.iter() {
+        required.amount = required.amount.checked_add(refund.amount)?;
+    }
+    let held = held_capital(deps, env, state, &required.denom)?;
+    if held.amount < required.amount {
+        return Err(ContractError::InsufficientFunds { held, required });
+    }
+    Ok(())
+}
+
+// every message a settlement goes out with, in order, shared by pay_out and
+// GetCloseMessageCount so the count can not drift from the call
+fn settlement_messages(
+    state: &State,
+    settlement: &Settlement,
+) -> StdResult<Vec<SubMsg<ProvenanceMsg>>> {
+    let mut withdrawals = vec![];
+    let mut settlements = vec![];
+    for (lp_capital_source, shares, capital) in settlement.called.iter().cloned() {
+        withdrawals.push(withdraw_coins(
+            shares.denom.clone(),
+            shares.amount.u128(),
+            shares.denom.clone(),
+            state.shares_recipient(&lp_capital_source),
+        )?);
+        if let Some(name) = &state.settlement_attribute {
+            settlements.push(add_json_attribute(
+                lp_capital_source,
+                name,
+                &SettlementAttribute {
+                    gp: state.gp.clone(),
+                    capital,
+                    shares,
+                },
+            )?);
+        }
+    }
+    if let Some((fee_recipient, fee)) = settlement
+        .shares_fee
+        .as_ref()
+        .filter(|(_, fee)| !fee.amount.is_zero())
+    {
+        withdrawals.push(withdraw_coins(
+            fee.denom.clone(),
+            fee.amount.u128(),
+            fee.denom.clone(),
+            fee_recipient.clone(),
+        )?);
+    }
+
+    let mut sends = settlement
+        .sends
+        .iter()
+        .map(|(_, recipient, coin)| {
+            send_capital(state.capital_cw20.as_ref(), recipient, coin.clone())
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    let confirmed = match settlement.complete {
+        true => sends.pop(),
+        false => None,
+    };
+
+    let mut messages = vec![];
+    for (recipient, refund) in settlement.refunds.iter() {
+        messages.push(SubMsg::new(send_capital(
+            state.capital_cw20.as_ref(),
+            recipient,
+            refund.clone(),
+        )?));
+    }
+    if !settlement.shares.amount.is_zero() {
+        messages.push(SubMsg::new(mint_marker_supply(
+            settlement.shares.amount.u128(),
+            settlement.shares.denom.clone(),
+        )?));
+    }
+    messages.extend(
+        withdrawals
+            .into_iter()
+            .chain(settlements)
+            .chain(sends)
+            .map(SubMsg::new),
+    );
+    if let Some(confirmed) = confirmed {
+        messages.push(SubMsg::reply_on_success(confirmed, CALL_CAPITAL_REPLY_ID));
+    }
+    Ok(messages)
+}
+
+// checks the sender may call capital now, shared by CallCapital, DrawCapital and ProposeClose
+fn check_caller(state: &State, env: &Env, sender: &Addr) -> Result<(), ContractError> {
+    check_callable(state)?;
+
+    if !may_call(state, sender) {
+        return Err(ContractError::Unauthorized {
+            sender: sender.clone(),
+        });
+    }
+
+    if past_due(state, env) {
+        return Err(ContractError::DeadlinePassed {
+            due_date_time: due_date_string(state.due_date_time),
+        });
+    }
+
+    if let Some(earliest) = earliest_call(state) {
+        if env.block.time < earliest {
+            return Err(ContractError::TooSoon {
+                earliest: earliest.seconds().to_string(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+// a gap between committing and calling stops both landing in the same block
+fn earliest_call(state: &State) -> Option<Timestamp> {
+    match (state.committed_at, state.min_commit_duration) {
+        (Some(committed_at), Some(duration)) => {
+            Some(saturating_plus_seconds(committed_at, duration))
+        }
+        _ => None,
+    }
+}
+
+pub fn try_call_capital(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    allocations: Option<Vec<Allocation>>,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    let mut state = config_read(deps.storage).load()?;
+    let prior_status = state.status.clone();
+
+    check_caller(&state, &env, &info.sender)?;
+
+    let settlement = settle(deps.as_ref(), &mut state, &allocations.unwrap_or_default())?;
+    pay_out(
+        deps,
+        &env,
+        info.sender,
+        "call_capital",
+        state,
+        prior_status,
+        settlement,
+    )
+}
+
+pub fn try_draw_capital(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    capital_amount: Uint128,
+    min_capital_out: Option<Uint128>,
+    max_shares_out: Option<Uint128>,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    let mut state = config_read(deps.storage).load()?;
+    let prior_status = state.status.clone();
+
+    check_caller(&state, &env, &info.sender)?;
+
+    let settlement = draw(deps.as_ref(), &mut state, capital_amount)?;
+
+    // guards against a draw priced differently than expected, before anything is saved
+    if let Some(min) = min_capital_out {
+        let capital = Coin {
+            denom: settlement.capital.denom.clone(),
+            amount: settlement.capital.amount.checked_sub(
+                settlement
+                    .fee
+                    .as_ref()
+                    .filter(|fee| fee.denom == settlement.capital.denom)
+                    .map_or(Uint128::zero(), |fee| fee.amount),
+            )?,
+        };
+        if capital.amount < min {
+            return Err(ContractError::CapitalOutTooLow {
+                min: Coin {
+                    denom: capital.denom.clone(),
+                    amount: min,
+                },
+                capital,
+            });
+        }
+    }
+    if let Some(max) = max_shares_out {
+        if settlement.shares.amount > max {
+            return Err(ContractError::SharesOutTooHigh {
+                shares: settlement.shares.clone(),
+                max: Coin {
+                    denom: settlement.shares.denom.clone(),
+                    amount: max,
+                },
+            });
+        }
+    }
+
+    pay_out(
+        deps,
+        &env,
+        info.sender,
+        "draw_capital",
+        state,
+        prior_status,
+        settlement,
+    )
+}
+
+pub fn try_propose_close(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    let mut state = config_read(deps.storage).load()?;
+    let prior_status = state.status.clone();
+
+    check_caller(&state, &env, &info.sender)?;
+
+    // the close settles the whole call, which a draw has already begun to settle
+    if !state.drawn.is_zero() {
+        return Err(ContractError::PartiallyDrawn {
+            drawn: Coin {
+                denom: state.subscriptions[0].capital.denom.clone(),
+                amount: state.drawn,
+            },
+        });
+    }
+
+    state.transition(Status::ClosePending)?;
+    for sub in state.subscriptions.iter_mut() {
+        sub.close_confirmed = false;
+    }
+    config(deps.storage).save(&state)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "propose_close")
+        .add_attribute("prior_status", prior_status.to_string())
+        .add_attribute("status", state.status.to_string())
+        .add_attribute("sender", info.sender))
+}
+
+// checks a close is proposed, shared by ConfirmClose and AbortClose
+fn check_closing(state: &State) -> Result<(), ContractError> {
+    if is_finished(state) {
+        return Err(ContractError::AlreadyFinished {
+            status: state.status.clone(),
+        });
+    }
+
+    if state.status != Status::ClosePending {
+        return Err(ContractError::WrongStatus {
+            current: state.status.clone(),
+            expected: Status::ClosePending,
+        });
+    }
+
+    Ok(())
+}
+
+pub fn try_confirm_close(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    let mut state = config_read(deps.storage).load()?;
+    let prior_status = state.status.clone();
+
+    check_closing(&state)?;
+
+    if past_due(&state, &env) {
+        return Err(ContractError::DeadlinePassed {
+            due_date_time: due_date_string(state.due_date_time),
+        });
+    }
+
+    // only the lps the close would call have a say in it, and only once
+    match state.subscription_mut(&info.sender) {
+        Some(sub) if sub.status == Status::CapitalCommitted && sub.close_confirmed => {
+            return Err(ContractError::AlreadyConfirmed {
+                lp_capital_source: info.sender,
+            })
+        }
+        Some(sub) if sub.status == Status::CapitalCommitted => sub.close_confirmed = true,
+        _ => {
+            return Err(ContractError::Unauthorized {
+                sender: info.sender,
+            })
+        }
+    };
+
+    // the last confirmation settles the call, as though it were called in full
+    let confirmed = state
+        .subscriptions
+        .iter()
+        .filter(|sub| sub.status == Status::CapitalCommitted)
+        .all(|sub| sub.close_confirmed);
+    if confirmed {
+        let settlement = settle(deps.as_ref(), &mut state, &[])?;
+        return pay_out(
+            deps,
+            &env,
+            info.sender,
+            "confirm_close",
+            state,
+            prior_status,
+            settlement,
+        );
+    }
+
+    state.transition(Status::ClosePending)?;
+    config(deps.storage).save(&state)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "confirm_close")
+        .add_attribute("prior_status", prior_status.to_string())
+        .add_attribute("status", state.status.to_string())
+        .add_attribute("sender", info.sender))
+}
+
+pub fn try_abort_close(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    let mut state = config_read(deps.storage).load()?;
+    let prior_status = state.status.clone();
+
+    check_closing(&state)?;
+
+    let committed_lp = state
+        .subscription(&info.sender)
+        .is_some_and(|sub| sub.status == Status::CapitalCommitted);
+    if !may_call(&state, &info.sender) && !committed_lp {
+        return Err(ContractError::Unauthorized {
+            sender: info.sender,
+        });
+    }
+
+    state.transition(Status::CapitalCommitted)?;
+    for sub in state.subscriptions.iter_mut() {
+        sub.close_confirmed = false;
+    }
+    config(deps.storage).save(&state)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "abort_close")
+        .add_attribute("prior_status", prior_status.to_string())
+        .add_attribute("status", state.status.to_string())
+        .add_attribute("sender", info.sender))
+}
+
+pub fn try_extend_due_date(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    new_due_date_time: String,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    let state = config_read(deps.storage).load()?;
+
+    if is_finished(&state) {
+        return Err(ContractError::AlreadyFinished {
+            status: state.status,
+        });
+    }
+
+    if info.sender != state.gp && !state.has_role(&info.sender, Role::Admin) {
+        return Err(ContractError::Unauthorized {
+            sender: info.sender,
+        });
+    }
+
+    let new_due_date = parse_due_date(&new_due_date_time)?;
+    if let Some(due_date_time) = state.due_date_time {
+        if new_due_date <= due_date_time {
+            return Err(ContractError::DueDateNotExtended {
+                due_date_time: due_date_string(state.due_date_time),
+                new_due_date_time,
+            });
+        }
+    }
+
+    config(deps.storage).save(&State {
+        due_date_time: Some(new_due_date),
+        ..state.clone()
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "extend_due_date")
+        .add_attribute("prior_status", state.status.to_string())
+        .add_attribute("status", state.status.to_string())
+        .add_attribute("sender", info.sender)
+        .add_attribute("prior_due_date_time", due_date_string(state.due_date_time))
+        .add_attribute("due_date_time", new_due_date_time))
+}
+
+pub fn try_update_terms(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    updates: Vec<TermsUpdate>,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    let mut state = config_read(deps.storage).load()?;
+
+    if !state.has_role(&info.sender, Role::Admin) {
+        return Err(ContractError::Unauthorized {
+            sender: info.sender,
+        });
+    }
+
+    if state.status != Status::PendingCapital {
+        return Err(ContractError::WrongStatus {
+            current: state.status,
+            expected: Status::PendingCapital,
+        });
+    }
+
+    // lps must never find the terms changed under capital they already sent
+    let committed = state.committed()?;
+    if !committed.amount.is_zero() {
+        return Err(ContractError::AlreadyCommitted { committed });
+    }
+
+    let mut response = Response::new()
+        .add_attribute("action", "update_terms")
+        .add_attribute("prior_status", state.status.to_string())
+        .add_attribute("status", state.status.to_string())
+        .add_attribute("sender", info.sender);
+
+    for update in updates {
+        let sub = state
+            .subscription_mut(&update.lp_capital_source)
+            .ok_or_else(|| ContractError::NotSubscribed {
+                address: update.lp_capital_source.clone(),
+            })?;
+        response = response.add_attribute("lp_capital_source", update.lp_capital_source);
+        sub.accepted = false;
+        if let Some(capital) = update.capital {
+            validate_coin(&capital)?;
+            response = response
+                .add_attribute("prior_capital", sub.capital.to_string())
+                .add_attribute("capital", capital.to_string());
+            sub.committed = Coin::new(0, capital.denom.clone());
+            sub.capital = capital;
+        }
+        if let Some(shares) = update.shares {
+            validate_coin(&shares)?;
+            response = response
+                .add_attribute("prior_shares", sub.shares.to_string())
+                .add_attribute("shares", shares.to_string());
+            sub.shares = shares;
+        }
+    }
+
+    // the updated terms must hold together the same way they did at instantiate
+    let first = state.subscriptions[0].clone();
+    if first.capital.denom == first.shares.denom {
+        return Err(ContractError::DuplicateDenom {
+            denom: first.capital.denom,
+        });
+    }
+    for sub in state.subscriptions.iter() {
+        if sub.capital.denom != first.capital.denom {
+            return Err(ContractError::MixedDenoms {
+                denom: sub.capital.denom.clone(),
+                expected: first.capital.denom,
+            });
+        }
+        if sub.shares.denom != first.shares.denom {
+            return Err(ContractError::MixedDenoms {
+                denom: sub.shares.denom.clone(),
+                expected: first.shares.denom,
+            });
+        }
+    }
+    if let Some(min_commitment) = &state.min_commitment {
+        if min_commitment.denom != first.capital.denom {
+            return Err(ContractError::MixedDenoms {
+                denom: min_commitment.denom.clone(),
+                expected: first.capital.denom,
+            });
+        }
+    }
+    if !state.allows_capital_denom(&first.capital.denom) {
+        return Err(ContractError::DenomNotAllowed {
+            denom: first.capital.denom,
+        });
+    }
+    if let Some(capital_cw20) = &state.capital_cw20 {
+        if first.capital.denom != capital_cw20.as_str() {
+            return Err(ContractError::WrongDenom {
+                got: first.capital.denom,
+                expected: capital_cw20.to_string(),
+            });
+        }
+    }
+    if let Some(commit_fee) = &state.commit_fee {
+        if commit_fee.denom == first.capital.denom || commit_fee.denom == first.shares.denom {
+            return Err(ContractError::InvalidCommitFee {
+                fee: commit_fee.clone(),
+            });
+        }
+    }
+
+    config(deps.storage).save(&state)?;
+
+    Ok(response)
+}
+
+pub fn try_update_shares_amount(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    lp_capital_source: Addr,
+    shares: Coin,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    let mut state = config_read(deps.storage).load()?;
+
+    if !state.has_role(&info.sender, Role::Admin) {
+        return Err(ContractError::Unauthorized {
+            sender: info.sender,
+        });
+    }
+
+    if state.status != Status::CapitalCommitted {
+        return Err(ContractError::WrongStatus {
+            current: state.status,
+            expected: Status::CapitalCommitted,
+        });
+    }
+
+    // shares already drawn were issued at the old price, which the rest must match
+    if !state.drawn.is_zero() {
+        return Err(ContractError::PartiallyDrawn {
+            drawn: Coin {
+                denom: state.subscriptions[0].capital.denom.clone(),
+                amount: state.drawn,
+            },
+        });
+    }
+
+    validate_coin(&shares)?;
+    let sub =
+        state
+            .subscription_mut(&lp_capital_source)
+            .ok_or_else(|| ContractError::NotSubscribed {
+                address: lp_capital_source.clone(),
+            })?;
+    if shares.denom != sub.shares.denom {
+        return Err(ContractError::WrongDenom {
+            got: shares.denom,
+            expected: sub.shares.denom.clone(),
+        });
+    }
+    let prior_shares = sub.shares.clone();
+    sub.shares = shares.clone();
+    config(deps.storage).save(&state)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_shares_amount")
+        .add_attribute("prior_status", state.status.to_string())
+        .add_attribute("status", state.status.to_string())
+        .add_attribute("sender", info.sender)
+        .add_attribute("lp_capital_source", lp_capital_source)
+        .add_attribute("prior_shares", prior_shares.to_string())
+        .add_attribute("shares", shares.to_string()))
+}
+
+pub fn try_update_subscription(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    lp_capital_source: Addr,
+    new_lp_capital_source: Addr,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    let mut state = config_read(deps.storage).load()?;
+
+    if !state.has_role(&info.sender, Role::Admin) {
+        return Err(ContractError::Unauthorized {
+            sender: info.sender,
+        });
+    }
+
+    if state.status != Status::PendingCapital {
+        return Err(ContractError::WrongStatus {
+            current: state.status,
+            expected: Status::PendingCapital,
+        });
+    }
+
+    // the same separation of roles as at instantiate
+    deps.api.addr_validate(new_lp_capital_source.as_str())?;
+    check_not_contract(&env, &new_lp_capital_source)?;
+    if new_lp_capital_source == state.gp || new_lp_capital_source == state.admin {
+        return Err(ContractError::DuplicateRole {
+            address: new_lp_capital_source,
+        });
+    }
+    if state.subscription(&new_lp_capital_source).is_some() {
+        return Err(ContractError::DuplicateSubscription {
+            lp_capital_source: new_lp_capital_source,
+        });
+    }
+
+    let sub =
+        state
+            .subscription_mut(&lp_capital_source)
+            .ok_or_else(|| ContractError::NotSubscribed {
+                address: lp_capital_source.clone(),
+            })?;
+    // capital already sent came from the old address, which refunds must still return to
+    if !sub.committed.amount.is_zero() {
+        return Err(ContractError::AlreadyCommitted {
+            committed: sub.committed.clone(),
+        });
+    }
+    sub.lp_capital_source = new_lp_capital_source.clone();
+    sub.accepted = false;
+    sub.close_confirmed = false;
+    config(deps.storage).save(&state)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_subscription")
+        .add_attribute("prior_status", state.status.to_string())
+        .add_attribute("status", state.status.to_string())
+        .add_attribute("sender", info.sender)
+        .add_attribute("prior_lp_capital_source", lp_capital_source)
+        .add_attribute("lp_capital_source", new_lp_capital_source))
+}
+
+pub fn try_attach_document(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    hash: String,
+    uri: Option<String>,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    let state = config_read(deps.storage).load()?;
+
+    if info.sender != state.gp && !state.has_role(&info.sender, Role::Admin) {
+        return Err(ContractError::Unauthorized {
+            sender: info.sender,
+        });
+    }
+
+    // a hex sha-256 digest
+    if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(ContractError::InvalidHash { hash });
+    }
+    if let Some(uri) = &uri {
+        if uri.len() > MAX_URI_LENGTH {
+            return Err(ContractError::UriTooLong {
+                length: uri.len(),
+                max: MAX_URI_LENGTH,
+            });
+        }
+    }
+
+    let mut refs = documents_read(deps.storage).may_load()?.unwrap_or_default();
+    if refs.len() >= MAX_DOCUMENTS {
+        return Err(ContractError::TooManyDocuments { max: MAX_DOCUMENTS });
+    }
+    refs.push(DocumentRef {
+        hash: hash.clone(),
+        uri,
+        timestamp: env.block.time,
+        sender: info.sender.clone(),
+    });
+    documents(deps.storage).save(&refs)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "attach_document")
+        .add_attribute("prior_status", state.status.to_string())
+        .add_attribute("status", state.status.to_string())
+        .add_attribute("sender", info.sender)
+        .add_attribute("hash", hash))
+}
+
+pub fn try_grant_role(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    address: Addr,
+    role: Role,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    let mut state = config_read(deps.storage).load()?;
+
+    if !state.has_role(&info.sender, Role::Admin) {
+        return Err(ContractError::Unauthorized {
+            sender: info.sender,
+        });
+    }
+
+    // lps never act for the gp or admin, as at instantiate
+    deps.api.addr_validate(address.as_str())?;
+    check_not_contract(&env, &address)?;
+    if state.subscription(&address).is_some() {
+        return Err(ContractError::DuplicateRole { address });
+    }
+
+    if state.has_role(&address, role.clone()) {
+        return Err(ContractError::RoleAlreadyGranted { address, role });
+    }
+
+    state.roles.push((address.clone(), role.clone()));
+    config(deps.storage).save(&state)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "grant_role")
+        .add_attribute("prior_status", state.status.to_string())
+        .add_attribute("status", state.status.to_string())
+        .add_attribute("sender", info.sender)
+        .add_attribute("address", address)
+        .add_attribute("role", role.to_string()))
+}
+
+// only granted roles can be revoked, the admin and authorized closers keep theirs
+pub fn try_revoke_role(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    address: Addr,
+    role: Role,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    let mut state = config_read(deps.storage).load()?;
+
+    if !state.has_role(&info.sender, Role::Admin) {
+        return Err(ContractError::Unauthorized {
+            sender: info.sender,
+        });
+    }
+
+    let granted = (address.clone(), role.clone());
+    if !state.roles.contains(&granted) {
+        return Err(ContractError::RoleNotGranted { address, role });
+    }
+
+    state.roles.retain(|held| held != &granted);
+    config(deps.storage).save(&state)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "revoke_role")
+        .add_attribute("prior_status", state.status.to_string())
+        .add_attribute("status", state.status.to_string())
+        .add_attribute("sender", info.sender)
+        .add_attribute("address", address)
+        .add_attribute("role", role.to_string()))
+}
+
+pub fn try_set_paused(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    paused: bool,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    let state = config_read(deps.storage).load()?;
+
+    if !state.has_role(&info.sender, Role::Admin) {
+        return Err(ContractError::Unauthorized {
+            sender: info.sender,
+        });
+    }
+
+    config(deps.storage).save(&State {
+        paused,
+        ..state.clone()
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", if paused { "pause" } else { "unpause" })
+        .add_attribute("prior_status", state.status.to_string())
+        .add_attribute("status", state.status.to_string())
+        .add_attribute("sender", info.sender))
+}
+
+pub fn try_emergency_withdraw(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: Addr,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    let mut state = config_read(deps.storage).load()?;
+    let prior_status = state.status.clone();
+
+    if !state.has_role(&info.sender, Role::Admin) {
+        return Err(ContractError::Unauthorized {
+            sender: info.sender,
+        });
+    }
+
+    // a called contract holds only what was settled, and that is no longer the lps'
+    if state.status == Status::CapitalCalled {
+        return Err(ContractError::AlreadyFinished {
+            status: state.status,
+        });
+    }
+
+    let ends = grace_period_end(&state).ok_or(ContractError::NoGracePeriod {})?;
+    if env.block.time <= ends {
+        return Err(ContractError::GracePeriodNotOver {
+            ends: ends.seconds().to_string(),
+        });
+    }
+
+    deps.api.addr_validate(recipient.as_str())?;
+
+    // everything held goes, so nothing is left for the lps to claim
+    let held_cw20 = held_cw20(deps.as_ref(), &env, &state)?;
+    let balances = deps.querier.query_all_balances(env.contract.address)?;
+    for sub in state.subscriptions.iter_mut() {
+        sub.committed.amount = Uint128::zero();
+        sub.status = Status::Cancelled;
+    }
+    let notify = state.status != Status::Cancelled;
+    if notify {
+        state.transition(Status::Cancelled)?;
+    }
+    if state.cancelled_at.is_none() {
+        state.cancelled_at = Some(env.block.time);
+    }
+    config(deps.storage).save(&state)?;
+
+    let mut response = Response::new()
+        .add_attribute("action", "emergency_withdraw")
+        .add_attribute("prior_status", prior_status.to_string())
+        .add_attribute("status", state.status.to_string())
+        .add_attribute("sender", info.sender)
+        .add_attribute("recipient", recipient.clone());
+    if !balances.is_empty() {
+        response = response
+            .add_attribute("transfer_type", "sweep")
+            .add_message(BankMsg::Send {
+                to_address: recipient.to_string(),
+                amount: balances,
+            });
+    }
+    if let Some(held) = held_cw20.filter(|held| !held.amount.is_zero()) {
+        response = response
+            .add_attribute("transfer_type", "sweep")
+            .add_message(send_capital(state.capital_cw20.as_ref(), &recipient, held)?);
+    }
+    if notify {
+        response = response.add_submessages(notify_observers(&state)?);
+    }
+
+    Ok(response)
+}
+
+pub fn try_update_admin(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    new_admin: Addr,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    let state = config_read(deps.storage).load()?;
+
+    if !state.has_role(&info.sender, Role::Admin) {
+        return Err(ContractError::Unauthorized {
+            sender: info.sender,
+        });
+    }
+
+    // the same separation of roles as at instantiate
+    deps.api.addr_validate(new_admin.as_str())?;
+    check_not_contract(&env, &new_admin)?;
+    if new_admin == state.gp || state.subscription(&new_admin).is_some() {
+        return Err(ContractError::DuplicateRole { address: new_admin });
+    }
+
+    config(deps.storage).save(&State {
+        admin: new_admin.clone(),
+        ..state.clone()
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_admin")
+        .add_attribute("prior_status", state.status.to_string())
+        .add_attribute("status", state.status.to_string())
+        .add_attribute("sender", info.sender)
+        .add_attribute("prior_admin", state.admin)
+        .add_attribute("admin", new_admin))
+}
+
+#[entry_point]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match (msg.id, msg.result) {
+        (CALL_CAPITAL_REPLY_ID, _) => {}
+        // a notification that failed is only noted, whatever it reported on stands
+        (NOTIFY_REPLY_ID, result) => {
+            let error = match result {
+                ContractResult::Err(error) => error,
+                ContractResult::Ok(_) => String::new(),
+            };
+            return Ok(Response::new()
+                .add_attribute("action", "notify_failed")
+                .add_attribute("error", error));
+        }
+        (id, _) => return Err(ContractError::UnknownReply { id }),
+    }
+
+    // only successful sends reply, so the settlement can now be finalized
+    let transition = pending_transition_read(deps.storage).load()?;
+    let mut state = config_read(deps.storage).load()?;
+    let prior_status = state.status.clone();
+    state.transition(transition.status.clone())?;
+    state.called_at = Some(transition.timestamp);
+    config(deps.storage).save(&state)?;
+    pending_transition(deps.storage).remove();
+
+    let sender = transition.sender.clone();
+    append_history(deps.storage, transition)?;
+
+    // the data replaces that of the call, which was still awaiting this
+    Ok(Response::new()
+        .set_data(execute_data(deps.as_ref())?)
+        .add_submessages(notify_observers(&state)?)
+        .add_submessages(notify_registry(&state, &prior_status)?)
+        .add_attribute("action", "confirm_call_capital")
+        .add_attribute("prior_status", prior_status.to_string())
+        .add_attribute("status", state.status.to_string())
+        .add_attribute("sender", sender))
+}
+
+#[entry_point]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::GetStatus {} => to_binary(&query_status(deps)?),
+        QueryMsg::GetDetailedStatus {} => to_binary(&query_detailed_status(deps)?),
+        QueryMsg::GetAdmin {} => to_binary(&query_admin(deps)?),
+        QueryMsg::GetTerms {} => to_binary(&query_terms(deps)?),
+        QueryMsg::IsExpired {} => to_binary(&query_expiry(deps, env)?),
+        QueryMsg::ValidateCapitalMarker {} => to_binary(&query_capital_marker(deps)?),
+        QueryMsg::GetCommitted {} => to_binary(&query_committed(deps)?),
+        QueryMsg::GetRemaining {} => to_binary(&query_remaining(deps)?),
+        QueryMsg::GetSubscriptions {} => to_binary(&query_subscriptions(deps)?),
+        QueryMsg::GetState {} => to_binary(&query_state(deps)?),
+        QueryMsg::GetHeldFunds {} => to_binary(&query_held_funds(deps, env)?),
+        QueryMsg::GetVersion {} => to_binary(&query_version(deps)?),
+        QueryMsg::GetHistory {} => to_binary(&query_history(deps)?),
+        QueryMsg::GetDocuments {} => to_binary(&query_documents(deps)?),
+        QueryMsg::GetPermissions { address } => to_binary(&query_permissions(deps, env, address)?),
+        QueryMsg::SimulateCall { allocations } => {
+            to_binary(&query_simulate_call(deps, allocations)?)
+        }
+        QueryMsg::GetCloseMessageCount {} => to_binary(&query_close_message_count(deps)?),
+        QueryMsg::GetSummary {} => to_binary(&query_summary(deps)?),
+        QueryMsg::GetCommitRequirement { address } => {
+            to_binary(&query_commit_requirement(deps, address)?)
+        }
+        QueryMsg::GetAvailableActions {} => to_binary(&query_available_actions(deps)?),
+        QueryMsg::GetRawConfigKey {} => to_binary(&Binary::from(config_raw_key())),
+        QueryMsg::GetParties {} => to_binary(&query_parties(deps)?),
+        QueryMsg::GetAuditTrail {} => to_binary(&query_audit_trail(deps)?),
+        QueryMsg::GetTimeline {} => to_binary(&query_timeline(deps)?),
+    }
+}
+
+// queries before instantiate would otherwise fail with a bare storage error
+fn load_state(deps: Deps) -> StdResult<State> {
+    config_read(deps.storage)
+        .may_load()?
+        .ok_or_else(not_instantiated)
+}
+
+fn not_instantiated() -> StdError {
+    StdError::generic_err(ContractError::NotInstantiated {}.to_string())
+}
+
+fn query_status(deps: Deps) -> StdResult<Status> {
+    let state = load_state(deps)?;
+    Ok(state.status)
+}
+
+fn query_detailed_status(deps: Deps) -> StdResult<DetailedStatus> {
+    let state = load_state(deps)?;
+    let committed = state.committed()?;
+    let mut capital = Uint128::zero();
+    for sub in state.subscriptions.iter() {
+        capital = capital.checked_add(sub.capital.amount)?;
+    }
+
+    // rounds down, so only a full commitment reads as 100
+    Ok(DetailedStatus {
+        status: state.status,
+        committed_ratio: committed.amount.multiply_ratio(100u128, capital).u128() as u8,
+        fully_committed: committed.amount >= capital,
+    })
+}
+
+fn query_admin(deps: Deps) -> StdResult<Addr> {
+    let state = load_state(deps)?;
+    Ok(state.admin)
+}
+
+fn query_expiry(deps: Deps, env: Env) -> StdResult<Expiry> {
+    let state = load_state(deps)?;
+    Ok(Expiry {
+        expired: past_due(&state, &env),
+        seconds_remaining: state
+            .due_date_time
+            .map(|due_date_time| due_date_time.seconds() as i64 - env.block.time.seconds() as i64),
+    })
+}
+
+fn query_terms(deps: Deps) -> StdResult<Terms> {
+    let state = load_state(deps)?;
+    Ok(Terms {
+        id: state.id,
+        gp: state.gp,
+        admin: state.admin,
+        subscriptions: state
+            .subscriptions
+            .into_iter()
+            .map(|sub| SubscriptionTerms {
+                lp_capital_source: sub.lp_capital_source,
+                capital: sub.capital,
+                shares: sub.shares,
+                shares_recipient: sub.shares_recipient,
+            })
+            .collect(),
+        due_date_time: state.due_date_time,
+        capital_decimals: state.capital_decimals,
+        shares_decimals: state.shares_decimals,
+    })
+}
+
+fn query_capital_marker(deps: Deps) -> StdResult<CapitalMarker> {
+    let state = load_state(deps)?;
+    let marker = ProvenanceQuerier::new(&deps.querier)
+        .get_marker_by_denom(state.subscriptions[0].capital.denom.clone())?;
+    Ok(CapitalMarker {
+        valid: marker.bank_sends_disabled() && marker.status == MarkerStatus::Active,
+        restricted: marker.bank_sends_disabled(),
+        denom: marker.denom,
+        address: marker.address,
+        status: marker.status,
+    })
+}
+
+fn query_committed(deps: Deps) -> StdResult<Coin> {
+    let state = load_state(deps)?;
+    state.committed()
+}
+
+fn query_remaining(deps: Deps) -> StdResult<Coin> {
+    let state = load_state(deps)?;
+    state.remaining()
+}
+
+fn query_commit_requirement(deps: Deps, address: Addr) -> StdResult<Vec<Coin>> {
+    let state = load_state(deps)?;
+    let sub = state.subscription(&address).ok_or_else(|| {
+        StdError::generic_err(ContractError::NotSubscribed { address }.to_string())
+    })?;
+
+    if is_finished(&state)
+        || state.status == Status::ClosePending
+        || sub.status != Status::PendingCapital
+    {
+        return Ok(vec![]);
+    }
+    let mut required = vec![Coin {
+        denom: sub.capital.denom.clone(),
+        amount: sub.capital.amount.checked_sub(sub.committed.amount)?,
+    }];
+    required.extend(state.commit_fee.clone());
+    Ok(required)
+}
+
+fn query_subscriptions(deps: Deps) -> StdResult<Vec<Subscription>> {
+    let state = load_state(deps)?;
+    Ok(state.subscriptions)
+}
+
+fn query_state(deps: Deps) -> StdResult<State> {
+    load_state(deps)
+}
+
+fn query_held_funds(deps: Deps, env: Env) -> StdResult<Vec<Coin>> {
+    let state = load_state(deps)?;
+    let mut held = deps
+        .querier
+        .query_all_balances(env.contract.address.clone())?;
+    held.extend(held_cw20(deps, &env, &state)?);
+    Ok(held)
+}
+
+// only the version is read, so state not yet migrated to the current shape is still reported
+fn query_version(deps: Deps) -> StdResult<u16> {
+    if deps.storage.get(&config_raw_key()).is_none() {
+        return Err(not_instantiated());
+    }
+    config_version(deps.storage)
+}
+
+fn query_documents(deps: Deps) -> StdResult<Vec<DocumentRef>> {
+    Ok(documents_read(deps.storage).may_load()?.unwrap_or_default())
+}
+
+fn query_history(deps: Deps) -> StdResult<Vec<TransitionRecord>> {
+    Ok(history_read(deps.storage).may_load()?.unwrap_or_default())
+}
+
+// mirrors the checks each handler makes before it touches any funds
+fn query_permissions(deps: Deps, env: Env, address: Addr) -> StdResult<Permissions> {
+    let state = load_state(deps)?;
+    let open = !state.paused && !is_finished(&state);
+    let manager = address == state.gp || state.has_role(&address, Role::Admin);
+    let past_due = past_due(&state, &env);
+    let may_cancel = may_cancel(&state, &env, &address);
+
+    Ok(Permissions {
+        can_cancel: open && may_cancel,
+        can_accept_terms: open
+            && state
+                .subscription(&address)
+                .is_some_and(|sub| sub.status == Status::PendingCapital && !sub.accepted),
+        can_commit: open
+            && state.status != Status::ClosePending
+            && state
+                .subscription(&address)
+                .is_some_and(|sub| sub.status == Status::PendingCapital && sub.accepted),
+        can_call: open
+            && state.status == Status::CapitalCommitted
+            && state.drawn.is_zero()
+            && may_call(&state, &address)
+            && !past_due
+            && earliest_call(&state).is_none_or(|earliest| env.block.time >= earliest),
+        can_extend_due_date: open && manager,
+        can_withdraw_expired: open
+            && matches!(
+                state.status,
+                Status::CapitalCommitted | Status::ClosePending
+            )
+            && state.subscription(&address).is_some()
+            && past_due,
+        can_update_terms: !state.paused
+            && state.has_role(&address, Role::Admin)
+            && state.status == Status::PendingCapital
+            && state.committed()?.amount.is_zero(),
+        can_reopen: !state.paused
+            && state.has_role(&address, Role::Admin)
+            && state.status == Status::Cancelled,
+        can_claim_refund: !state.paused
+            && state.status == Status::Cancelled
+            && state
+                .subscription(&address)
+                .is_some_and(|sub| !sub.committed.amount.is_zero()),
+        can_pause: state.has_role(&address, Role::Admin),
+        can_emergency_withdraw: state.has_role(&address, Role::Admin)
+            && state.status != Status::CapitalCalled
+            && grace_period_end(&state).is_some_and(|ends| env.block.time > ends),
+        can_update_admin: state.has_role(&address, Role::Admin),
+    })
+}
+
+fn query_available_actions(deps: Deps) -> StdResult<Vec<String>> {
+    let state = load_state(deps)?;
+
+    // the same actions that work while paused, in the order execute dispatches them
+    let mut actions = vec![
+        if state.paused { "unpause" } else { "pause" },
+        "update_admin",
+    ];
+    if state.status != Status::CapitalCalled {
+        actions.push("emergency_withdraw");
+    }
+    if state.paused {
+        return Ok(actions.into_iter().map(String::from).collect());
+    }
+
+    // cw20 capital is committed by sending the tokens rather than through an allowance
+    let commit_from = if state.capital_cw20.is_some() {
+        "receive"
+    } else {
+        "commit_capital_from"
+    };
+    actions.extend(match state.status {
+        Status::PendingCapital => vec![
+            "cancel",
+            "accept_terms",
+            "commit_capital",
+            commit_from,
+            "update_terms",
+            "update_subscription",
+            "extend_due_date",
+        ],
+        Status::CapitalCommitted => {
+            let mut actions = vec!["cancel"];
+            // other lps may still commit until the call
+            if !state.remaining()?.amount.is_zero() {
+                actions.extend(["accept_terms", "commit_capital", commit_from]);
+            }
+            actions.extend(["withdraw_expired", "draw_capital"]);
+            // once drawn, the rest can only be drawn the same way
+            if state.drawn.is_zero() {
+                actions.extend(["call_capital", "propose_close", "update_shares_amount"]);
+            }
+            actions.push("extend_due_date");
+            actions
+        }
+        Status::ClosePending => vec![
+            "cancel",
+            "withdraw_expired",
+            "confirm_close",
+            "abort_close",
+            "extend_due_date",
+        ],
+        Status::Cancelled => vec!["claim_refund", "reopen"],
+        Status::CapitalCalled => vec![],
+    });
+    actions.extend(["attach_document", "grant_role", "revoke_role"]);
+
+    Ok(actions.into_iter().map(String::from).collect())
+}
+
+fn query_simulate_call(
+    deps: Deps,
+    allocations: Option<Vec<Allocation>>,
+) -> StdResult<Vec<Transfer>> {
+    // settles a copy of the state that is never saved
+    let mut state = load_state(deps)?;
+    let settlement = check_callable(&state)
+        .and_then(|_| settle(deps, &mut state, &allocations.unwrap_or_default()))
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+
+    let shares = settlement
+        .called
+        .into_iter()
+        .map(|(lp_capital_source, shares, _)| {
+            ("shares", state.shares_recipient(&lp_capital_source), shares)
+        })
+        .chain(
+            settlement
+                .shares_fee
+                .filter(|(_, fee)| !fee.amount.is_zero())
+                .map(|(fee_recipient, fee)| ("fee", fee_recipient, fee)),
+        );
+    let refunds = settlement
+        .refunds
+        .into_iter()
+        .map(|(lp_capital_source, refund)| ("refund", lp_capital_source, refund));
+    Ok(refunds
+        .chain(shares)
+        .chain(settlement.sends)
+        .map(|(transfer_type, recipient, coin)| Transfer {
+            transfer_type: transfer_type.to_string(),
+            recipient,
+            coin,
+        })
+        .collect())
+}
+
+fn query_close_message_count(deps: Deps) -> StdResult<u32> {
+    // settles a copy of the state that is never saved, exactly as CallCapital would
+    let mut state = load_state(deps)?;
+    let prior_status = state.status.clone();
+    let settlement = check_callable(&state)
+        .and_then(|_| settle(deps, &mut state, &[]))
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+    let settlement_count = settlement_messages(&state, &settlement)?.len();
+
+    // the call settles in the same transaction, telling the observers and registry
+    state.status = Status::CapitalCalled;
+    let notification_count = notify_observers::<ProvenanceMsg>(&state)?.len()
+        + notify_registry::<ProvenanceMsg>(&state, &prior_status)?
+            .iter()
+            .count();
+    Ok((settlement_count + notification_count) as u32)
+}
+
+fn query_summary(deps: Deps) -> StdResult<Summary> {
+    let state = load_state(deps)?;
+    Ok(Summary {
+        committed: state.committed()?,
+        status: state.status,
+        gp: state.gp,
+        admin: state.admin,
+        subscriptions: state.subscriptions,
+        due_date_time: state.due_date_time,
+        committed_at: state.committed_at,
+        called_at: state.called_at,
+        cancelled_at: state.cancelled_at,
+    })
+}
+
+fn query_parties(deps: Deps) -> StdResult<Parties> {
+    let state = load_state(deps)?;
+    let capital_denom = state.subscriptions[0].capital.denom.clone();
+    let shares_denom = state.subscriptions[0].shares.denom.clone();
+
+    // called capital and fees, then refunds and shares for each lp
+    let mut recipients = vec![capital_recipient(deps, &state, &shares_denom)?];
+    recipients.extend(state.fee_recipient.clone());
+    for sub in state.subscriptions.iter() {
+        recipients.push(sub.refund_recipient().clone());
+        recipients.push(state.shares_recipient(&sub.lp_capital_source));
+    }
+
+    // commit fees pass through to the fee recipient
+    let mut parties = Parties {
+        recipients: vec![],
+        denoms: vec![capital_denom, shares_denom],
+    };
+    parties
+        .denoms
+        .extend(state.commit_fee.map(|commit_fee| commit_fee.denom));
+    for recipient in recipients {
+        if !parties.recipients.contains(&recipient) {
+            parties.recipients.push(recipient);
+        }
+    }
+    Ok(parties)
+}
+
+fn query_audit_trail(deps: Deps) -> StdResult<AuditTrail> {
+    let state = load_state(deps)?;
+    Ok(AuditTrail {
+        id: state.id,
+        gp: state.gp,
+        admin: state.admin,
+        parties: query_parties(deps)?,
+        history: query_history(deps)?,
+        documents: query_documents(deps)?,
+    })
+}
+
+fn query_timeline(deps: Deps) -> StdResult<Vec<Milestone>> {
+    let state = load_state(deps)?;
+
+    // contracts migrated from before the timestamps were kept have reached their milestones
+    // without recording when
+    let committed = matches!(
+        state.status,
+        Status::CapitalCommitted | Status::ClosePending | Status::CapitalCalled
+    );
+    let mut timeline = vec![
+        Milestone {
+            name: "instantiated".into(),
+            reached: true,
+            timestamp: state.instantiated_at,
+        },
+        Milestone {
+            name: "committed".into(),
+            reached: committed || state.committed_at.is_some(),
+            timestamp: state.committed_at,
+        },
+    ];
+    timeline.push(match state.status {
+        Status::Cancelled => Milestone {
+            name: "cancelled".into(),
+            reached: true,
+            timestamp: state.cancelled_at,
+        },
+        _ => Milestone {
+            name: "called".into(),
+            reached: state.status == Status::CapitalCalled,
+            timestamp: state.called_at,
+        },
+    });
+    Ok(timeline)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{StateV0, CONFIG_KEY};
+    use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{
+        attr, coins, from_binary, from_slice, Addr, Coin, ContractResult, CosmosMsg, OwnedDeps,
+        Querier, QuerierResult, QueryRequest, ReplyOn, Storage, SubMsgExecutionResponse,
+        SystemResult, WasmQuery,
+    };
+    use cosmwasm_storage::singleton;
+    use cw2::get_contract_version;
+    use provwasm_mocks::{mock_dependencies, must_read_binary_file, ProvenanceMockQuerier};
+    use provwasm_std::{
+        AttributeMsgParams, Marker, MarkerMsgParams, MarkerType, ProvenanceMsgParams,
+        ProvenanceQuery, ProvenanceRoute,
+    };
+
+    // answers every wasm query with the contract's cw20 balance, which the mocks can not
+    struct Cw20Querier {
+        base: ProvenanceMockQuerier,
+        balance: Uint128,
+    }
+
+    impl Querier for Cw20Querier {
+        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+            match from_slice::<QueryRequest<ProvenanceQuery>>(bin_request) {
+                Ok(QueryRequest::Wasm(WasmQuery::Smart { .. })) => {
+                    SystemResult::Ok(ContractResult::Ok(
+                        to_binary(&BalanceResponse {
+                            balance: self.balance,
+                        })
+                        .unwrap(),
+                    ))
+                }
+                _ => self.base.raw_query(bin_request),
+            }
+        }
+    }
+
+    fn cw20_dependencies(balance: u128) -> OwnedDeps<MockStorage, MockApi, Cw20Querier> {
+        OwnedDeps {
+            storage: MockStorage::default(),
+            api: MockApi::default(),
+            querier: Cw20Querier {
+                base: mock_dependencies(&[]).querier,
+                balance: Uint128::new(balance),
+            },
+        }
+    }
+
+    fn lp_terms() -> SubscriptionTerms {
+        SubscriptionTerms {
+            lp_capital_source: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+            capital: Coin::new(1000000, "cfigure"),
+            shares: Coin::new(10, "fund-coin"),
+            shares_recipient: None,
+        }
+    }
+
+    fn inst_msg() -> InstantiateMsg {
+        inst_msg_with(vec![lp_terms()])
+    }
+
+    fn inst_msg_with(subscriptions: Vec<SubscriptionTerms>) -> InstantiateMsg {
+        InstantiateMsg {
+            id: "capital-call-1".into(),
+            admin: Addr::unchecked("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000"),
+            subscriptions,
+            capital_cw20: None,
+            capital_recipient: None,
+            due_date_time: Some("1600000000".into()),
+            settlement_attribute: None,
+            allowed_capital_denoms: vec![],
+            fee_bps: None,
+            fee_basis: None,
+            fee_recipient: None,
+            commit_fee: None,
+            authorized_closers: vec![],
+            observers: vec![],
+            registry: None,
+            auto_refund: None,
+            initial_status: None,
+            min_commitment: None,
+            emergency_grace_period: None,
+            min_commit_duration: None,
+            capital_decimals: None,
+            shares_decimals: None,
+        }
+    }
+
+    fn bank_sends<T: Clone + fmt::Debug + PartialEq + JsonSchema>(
+        res: &Response<T>,
+    ) -> Vec<(String, Vec<Coin>)> {
+        res.messages
+            .iter()
+            .filter_map(|sub_msg| match &sub_msg.msg {
+                CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+                    Some((to_address.clone(), amount.clone()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn capital_reply() -> Reply {
+        Reply {
+            id: CALL_CAPITAL_REPLY_ID,
+            result: ContractResult::Ok(SubMsgExecutionResponse {
+                events: vec![],
+                data: None,
+            }),
+        }
+    }
+
+    fn accept_terms(mut deps: DepsMut) {
+        let state = config_read(deps.storage).load().unwrap();
+        for sub in state.subscriptions {
+            let info = mock_info(sub.lp_capital_source.as_str(), &[]);
+            execute(deps.branch(), mock_env(), info, HandleMsg::AcceptTerms {}).unwrap();
+        }
+    }
+
+    fn env_at(seconds: u64) -> Env {
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(seconds);
+        env
+    }
+
+    #[test]
+    fn initialization() {
+        let mut deps = mock_dependencies(&[]);
+        let info = mock_info("creator", &[]);
+
+        // we can just call .unwrap() to assert this was a success
+        let res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // it worked, let's query the state
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
+        let status: Status = from_binary(&res).unwrap();
+        assert_eq!(Status::PendingCapital, status);
+
+        // and it reports the current state shape
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetVersion {}).unwrap();
+        let version: u16 = from_binary(&res).unwrap();
+        assert_eq!(1, version);
+    }
+
+    #[test]
+    fn query_full_state() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(400000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetState {}).unwrap();
+        let state: State = from_binary(&res).unwrap();
+        assert_eq!(Addr::unchecked("creator"), state.gp);
+        assert_eq!(
+            Coin::new(1000000, "cfigure"),
+            state.subscriptions[0].capital
+        );
+        assert_eq!(Coin::new(10, "fund-coin"), state.subscriptions[0].shares);
+
+        // the snapshot should agree with the individual queries
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
+        let status: Status = from_binary(&res).unwrap();
+        assert_eq!(status, state.status);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCommitted {}).unwrap();
+        let committed: Coin = from_binary(&res).unwrap();
+        assert_eq!(committed, state.committed().unwrap());
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSubscriptions {}).unwrap();
+        let subscriptions: Vec<Subscription> = from_binary(&res).unwrap();
+        assert_eq!(subscriptions, state.subscriptions);
+    }
+
+    #[test]
+    fn query_full_summary() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), env_at(1590000000), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSummary {}).unwrap();
+        let summary: Summary = from_binary(&res).unwrap();
+        assert_eq!(
+            Some(Timestamp::from_seconds(1590000000)),
+            summary.committed_at
+        );
+
+        // the summary should agree with the individual queries
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
+        let status: Status = from_binary(&res).unwrap();
+        assert_eq!(status, summary.status);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCommitted {}).unwrap();
+        let committed: Coin = from_binary(&res).unwrap();
+        assert_eq!(committed, summary.committed);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSubscriptions {}).unwrap();
+        let subscriptions: Vec<Subscription> = from_binary(&res).unwrap();
+        assert_eq!(subscriptions, summary.subscriptions);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetState {}).unwrap();
+        let state: State = from_binary(&res).unwrap();
+        assert_eq!(state.gp, summary.gp);
+        assert_eq!(state.admin, summary.admin);
+        assert_eq!(state.due_date_time, summary.due_date_time);
+        assert_eq!(state.committed_at, summary.committed_at);
+        assert_eq!(state.called_at, summary.called_at);
+        assert_eq!(state.cancelled_at, summary.cancelled_at);
+    }
+
+    #[test]
+    fn query_held_funds() {
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetHeldFunds {}).unwrap();
+        let held: Vec<Coin> = from_binary(&res).unwrap();
+        assert_eq!(coins(1000000, "cfigure"), held);
+    }
+
+    #[test]
+    fn commit_capital_from() {
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &[]);
+        let msg = HandleMsg::CommitCapitalFrom {
+            owner: Addr::unchecked("owner"),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![CosmosMsg::Custom(ProvenanceMsg {
+                route: ProvenanceRoute::Marker,
+                params: ProvenanceMsgParams::Marker(MarkerMsgParams::TransferMarkerCoins {
+                    coin: Coin::new(1000000, "cfigure"),
+                    to: Addr::unchecked(MOCK_CONTRACT_ADDR),
+                    from: Addr::unchecked("owner"),
+                }),
+                version: "2.0.0".into(),
+            })],
+            res.messages
+                .iter()
+                .map(|sub_msg| sub_msg.msg.clone())
+                .collect::<Vec<_>>()
+        );
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
+        let status: Status = from_binary(&res).unwrap();
+        assert_eq!(Status::CapitalCommitted, status);
+
+        // the capital goes back to the owner it came from
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::Cancel { reason: None };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![("owner".to_string(), coins(1000000, "cfigure"))],
+            bank_sends(&res)
+        );
+    }
+
+    #[test]
+    fn commit_capital_min_commitment() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let msg = InstantiateMsg {
+            min_commitment: Some(Coin::new(300000, "cfigure")),
+            ..inst_msg()
+        };
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        // a deposit below the minimum is turned away
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(100000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::BelowMinimum {
+                deposit: Coin::new(100000, "cfigure"),
+                min_commitment: Coin::new(300000, "cfigure"),
+            },
+            err
+        );
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(800000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // the final deposit completing the capital may be smaller
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(200000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
+        let status: Status = from_binary(&res).unwrap();
+        assert_eq!(Status::CapitalCommitted, status);
+    }
+
+    #[test]
+    fn commit_capital() {
+        let mut deps = mock_dependencies(&coins(2, "token"));
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        // lp can commit capital
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![
+                attr("action", "commit_capital"),
+                attr("prior_status", "pending_capital"),
+                attr("status", "capital_committed"),
+                attr("sender", "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+                attr("capital", "1000000cfigure"),
+                attr("committed", "1000000cfigure"),
+            ],
+            res.attributes
+        );
+
+        // should be in capital commited state
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
+        let status: Status = from_binary(&res).unwrap();
+        assert_eq!(Status::CapitalCommitted, status);
+    }
+
+    #[test]
+    fn accept_terms_before_commit() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+
+        // lp can not commit against terms it has not accepted
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::TermsNotAccepted {
+                lp_capital_source: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7")
+            },
+            err
+        );
+
+        // only lps can accept terms
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::AcceptTerms {};
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("creator")
+            },
+            err
+        );
+
+        let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &[]);
+        let msg = HandleMsg::AcceptTerms {};
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![
+                attr("action", "accept_terms"),
+                attr("prior_status", "pending_capital"),
+                attr("status", "pending_capital"),
+                attr("sender", "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+            ],
+            res.attributes
+        );
+
+        // accepting again is refused and leaves no further history
+        let history = |deps: Deps| {
+            let res = query(deps, mock_env(), QueryMsg::GetHistory {}).unwrap();
+            from_binary::<Vec<TransitionRecord>>(&res).unwrap().len()
+        };
+        let recorded = history(deps.as_ref());
+        let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &[]);
+        let msg = HandleMsg::AcceptTerms {};
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::AlreadyAccepted {
+                lp_capital_source: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7")
+            },
+            err
+        );
+        assert_eq!(recorded, history(deps.as_ref()));
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // there is nothing left to accept once committed
+        let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &[]);
+        let msg = HandleMsg::AcceptTerms {};
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::WrongStatus {
+                current: Status::CapitalCommitted,
+                expected: Status::PendingCapital,
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn commit_capital_in_installments() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        // lp can commit part of the capital
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(400000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // should still be pending capital with the running total recorded
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
+        let status: Status = from_binary(&res).unwrap();
+        assert_eq!(Status::PendingCapital, status);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCommitted {}).unwrap();
+        let committed: Coin = from_binary(&res).unwrap();
+        assert_eq!(Coin::new(400000, "cfigure"), committed);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRemaining {}).unwrap();
+        let remaining: Coin = from_binary(&res).unwrap();
+        assert_eq!(Coin::new(600000, "cfigure"), remaining);
+
+        // overpaying the final installment commits the remainder and refunds the rest
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(600001, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![
+                attr("action", "commit_capital"),
+                attr("prior_status", "pending_capital"),
+                attr("status", "capital_committed"),
+                attr("sender", "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+                attr("capital", "600000cfigure"),
+                attr("committed", "1000000cfigure"),
+                attr("transfer_type", "refund"),
+                attr("refund", "1cfigure"),
+            ],
+            res.attributes
+        );
+        assert_eq!(
+            vec![(
+                "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7".to_string(),
+                coins(1, "cfigure")
+            )],
+            bank_sends(&res)
+        );
+
+        // should be in capital committed state
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
+        let status: Status = from_binary(&res).unwrap();
+        assert_eq!(Status::CapitalCommitted, status);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCommitted {}).unwrap();
+        let committed: Coin = from_binary(&res).unwrap();
+        assert_eq!(Coin::new(1000000, "cfigure"), committed);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRemaining {}).unwrap();
+        let remaining: Coin = from_binary(&res).unwrap();
+        assert_eq!(Coin::new(0, "cfigure"), remaining);
+    }
+
+    #[test]
+    fn commit_capital_idempotency_key() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(400000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: Some("installment-1".to_string()),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+
+        // a retry with the same key is turned away rather than counted twice
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+        assert_eq!(
+            ContractError::DuplicateCommit {
+                key: "installment-1".to_string()
+            },
+            err
+        );
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCommitted {}).unwrap();
+        let committed: Coin = from_binary(&res).unwrap();
+        assert_eq!(Coin::new(400000, "cfigure"), committed);
+
+        // a new key commits as usual
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: Some("installment-2".to_string()),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCommitted {}).unwrap();
+        let committed: Coin = from_binary(&res).unwrap();
+        assert_eq!(Coin::new(800000, "cfigure"), committed);
+
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: Some(String::new()),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::InvalidIdempotencyKey {
+                key: String::new(),
+                max: 64
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn commit_keys_bounded_per_lp() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = inst_msg_with(vec![
+            lp_terms(),
+            SubscriptionTerms {
+                lp_capital_source: Addr::unchecked("lp2"),
+                capital: Coin::new(500000, "cfigure"),
+                shares: Coin::new(5, "fund-coin"),
+                shares_recipient: None,
+            },
+        ]);
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        let commit = |deps: &mut OwnedDeps<_, _, _>, sender: &str, key: &str| {
+            let info = mock_info(sender, &coins(1, "cfigure"));
+            let msg = HandleMsg::CommitCapital {
+                idempotency_key: Some(key.to_string()),
+            };
+            execute(deps.as_mut(), mock_env(), info, msg)
+        };
+        commit(
+            &mut deps,
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            "retry",
+        )
+        .unwrap();
+        for key in 0..=64 {
+            commit(&mut deps, "lp2", &key.to_string()).unwrap();
+        }
+
+        // another lp's keys never push this lp's out
+        let err = commit(
+            &mut deps,
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            "retry",
+        );
+        assert_eq!(
+            ContractError::DuplicateCommit {
+                key: "retry".to_string()
+            },
+            err.unwrap_err()
+        );
+
+        // while each lp only keeps its most recent keys
+        commit(&mut deps, "lp2", "0").unwrap();
+        let err = commit(&mut deps, "lp2", "64");
+        assert_eq!(
+            ContractError::DuplicateCommit {
+                key: "64".to_string()
+            },
+            err.unwrap_err()
+        );
+    }
+
+    #[test]
+    fn commit_capital_errors() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        // only the lp can commit capital
+        let info = mock_info("creator", &coins(1000000, "cfigure"));
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CommitCapital {
+                idempotency_key: None,
+            },
+        );
+        assert_eq!(
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("creator")
+            },
+            err.unwrap_err()
+        );
+
+        // capital must be attached
+        let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &[]);
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CommitCapital {
+                idempotency_key: None,
+            },
+        );
+        assert_eq!(ContractError::NoFundsProvided {}, err.unwrap_err());
+
+        // nothing but the capital can be attached
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &[Coin::new(1000000, "cfigure"), Coin::new(5, "junk")],
+        );
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CommitCapital {
+                idempotency_key: None,
+            },
+        );
+        assert_eq!(
+            ContractError::ExtraFunds {
+                denom: "junk".into()
+            },
+            err.unwrap_err()
+        );
+
+        // capital must be in the required denom
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "nhash"),
+        );
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CommitCapital {
+                idempotency_key: None,
+            },
+        );
+        assert_eq!(
+            ContractError::WrongDenom {
+                got: "nhash".into(),
+                expected: "cfigure".into(),
+            },
+            err.unwrap_err()
+        );
+
+        // capital can not be called before it is committed
+        let info = mock_info("creator", &[]);
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CallCapital { allocations: None },
+        );
+        assert_eq!(
+            ContractError::WrongStatus {
+                current: Status::PendingCapital,
+                expected: Status::CapitalCommitted,
+            },
+            err.unwrap_err()
+        );
+
+        // nothing can be committed once cancelled
+        let info = mock_info("creator", &[]);
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::Cancel { reason: None },
+        )
+        .unwrap();
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CommitCapital {
+                idempotency_key: None,
+            },
+        );
+        assert_eq!(
+            ContractError::AlreadyFinished {
+                status: Status::Cancelled,
+            },
+            err.unwrap_err()
+        );
+
+        // and it can not be cancelled twice
+        let info = mock_info("creator", &[]);
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::Cancel { reason: None },
+        );
+        assert_eq!(
+            ContractError::AlreadyFinished {
+                status: Status::Cancelled,
+            },
+            err.unwrap_err()
+        );
+    }
+
+    #[test]
+    fn cancel() {
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        // lp can commit capital
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // gp can no longer cancel committed capital
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::Cancel { reason: None };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("creator")
+            },
+            err
+        );
+
+        // admin can cancel capital call
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::Cancel { reason: None };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![
+                attr("action", "cancel"),
+                attr("prior_status", "capital_committed"),
+                attr("status", "cancelled"),
+                attr("sender", "tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000"),
+                attr("transfer_type", "refund"),
+                attr("refund", "1000000cfigure"),
+            ],
+            _res.attributes
+        );
+
+        // should be in pending capital state
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
+        let status: Status = from_binary(&res).unwrap();
+        assert_eq!(Status::Cancelled, status);
+
+        // should send stable coin back to lp
+        let (to_address, amount) = _res
+            .messages
+            .iter()
+            .find_map(|sub_msg| match &sub_msg.msg {
+                CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => Some((to_address, amount)),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", to_address);
+        assert_eq!(1000000, u128::from(amount[0].amount));
+        assert_eq!("cfigure", amount[0].denom);
+    }
+
+    #[test]
+    fn commit_and_refund_cw20_capital() {
+        let mut deps = cw20_dependencies(1000000);
+
+        let mut msg = inst_msg_with(vec![SubscriptionTerms {
+            capital: Coin::new(1000000, "cw20token"),
+            ..lp_terms()
+        }]);
+        msg.capital_cw20 = Some(Addr::unchecked("cw20token"));
+        msg.auto_refund = Some(false);
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        let receive = HandleMsg::Receive(Cw20ReceiveMsg {
+            sender: "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7".into(),
+            amount: Uint128::new(1000000),
+            msg: to_binary(&ReceiveMsg::CommitCapital {
+                idempotency_key: None,
+            })
+            .unwrap(),
+        });
+
+        // only the capital token can report a receive
+        let info = mock_info("othertoken", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, receive.clone()).unwrap_err();
+        assert_eq!(
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("othertoken")
+            },
+            err
+        );
+
+        // the received tokens are committed for the lp who sent them
+        let info = mock_info("cw20token", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, receive).unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(Status::CapitalCommitted, state.status);
+        assert_eq!(
+            Coin::new(1000000, "cw20token"),
+            state.subscriptions[0].committed
+        );
+
+        // a refund goes back as a cw20 transfer
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::Cancel { reason: None };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, HandleMsg::ClaimRefund {}).unwrap();
+        assert_eq!(
+            vec![SubMsg::new(WasmMsg::Execute {
+                contract_addr: "cw20token".into(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7".into(),
+                    amount: Uint128::new(1000000),
+                })
+                .unwrap(),
+                funds: vec![],
+            })],
+            res.messages
+        );
+    }
+
+    #[test]
+    fn instantiate_cw20_capital_must_be_the_capital_denom() {
+        let mut deps = mock_dependencies(&[]);
+
+        let mut msg = inst_msg();
+        msg.capital_cw20 = Some(Addr::unchecked("cw20token"));
+        let info = mock_info("creator", &[]);
+        let err = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::WrongDenom {
+                got: "cfigure".into(),
+                expected: "cw20token".into()
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn instantiate_cw20_capital_without_commit_fee() {
+        let mut deps = mock_dependencies(&[]);
+
+        let mut msg = inst_msg_with(vec![SubscriptionTerms {
+            capital: Coin::new(1000000, "cw20token"),
+            ..lp_terms()
+        }]);
+        msg.capital_cw20 = Some(Addr::unchecked("cw20token"));
+        msg.fee_recipient = Some(Addr::unchecked("platform"));
+        msg.commit_fee = Some(Coin::new(100, "nhash"));
+        let info = mock_info("creator", &[]);
+        let err = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::InvalidCommitFee {
+                fee: Coin::new(100, "nhash")
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn commit_cw20_capital_from() {
+        let mut deps = mock_dependencies(&[]);
+
+        let mut msg = inst_msg_with(vec![SubscriptionTerms {
+            capital: Coin::new(1000000, "cw20token"),
+            ..lp_terms()
+        }]);
+        msg.capital_cw20 = Some(Addr::unchecked("cw20token"));
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &[]);
+        let msg = HandleMsg::CommitCapitalFrom {
+            owner: Addr::unchecked("owner"),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::Cw20Capital {
+                token: Addr::unchecked("cw20token")
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn cancel_refunds_at_most_what_is_held() {
+        // capital bootstrapped as committed, but only part of it ever arrived
+        let mut deps = mock_dependencies(&coins(400000, "cfigure"));
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        let mut state = config_read(&deps.storage).load().unwrap();
+        state.status = Status::CapitalCommitted;
+        state.subscriptions[0].status = Status::CapitalCommitted;
+        state.subscriptions[0].committed = Coin::new(1000000, "cfigure");
+        config(&mut deps.storage).save(&state).unwrap();
+
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::Cancel { reason: None };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![(
+                "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7".to_string(),
+                coins(400000, "cfigure")
+            )],
+            bank_sends(&res)
+        );
+        assert!(res.attributes.contains(&attr("refund", "400000cfigure")));
+
+        // the shortfall stays committed, and claiming it is capped at what is held too
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCommitted {}).unwrap();
+        let committed: Coin = from_binary(&res).unwrap();
+        assert_eq!(Coin::new(600000, "cfigure"), committed);
+        deps.querier
+            .base
+            .update_balance(MOCK_CONTRACT_ADDR, coins(100000, "cfigure"));
+        let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &[]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            HandleMsg::ClaimRefund {},
+        )
+        .unwrap();
+        assert_eq!(
+            vec![(
+                "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7".to_string(),
+                coins(100000, "cfigure")
+            )],
+            bank_sends(&res)
+        );
+        deps.querier
+            .base
+            .update_balance(MOCK_CONTRACT_ADDR, coins(500000, "cfigure"));
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            HandleMsg::ClaimRefund {},
+        )
+        .unwrap();
+        assert_eq!(
+            vec![(
+                "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7".to_string(),
+                coins(500000, "cfigure")
+            )],
+            bank_sends(&res)
+        );
+        let err = execute(deps.as_mut(), mock_env(), info, HandleMsg::ClaimRefund {}).unwrap_err();
+        assert_eq!(ContractError::NoRefund {}, err);
+
+        // with nothing held there is nothing to send
+        let mut deps = mock_dependencies(&[]);
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        config(&mut deps.storage).save(&state).unwrap();
+
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::Cancel { reason: None };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert!(res.messages.is_empty());
+    }
+
+    #[test]
+    fn cancel_multiple_subscriptions() {
+        let mut deps = mock_dependencies(&coins(1200000, "cfigure"));
+
+        let msg = inst_msg_with(vec![
+            lp_terms(),
+            SubscriptionTerms {
+                lp_capital_source: Addr::unchecked("lp2"),
+                capital: Coin::new(500000, "cfigure"),
+                shares: Coin::new(5, "fund-coin"),
+                shares_recipient: None,
+            },
+        ]);
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info("lp2", &coins(200000, "cfigure"));
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // each lp gets back what it committed itself
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::Cancel { reason: None };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![
+                (
+                    "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7".to_string(),
+                    coins(1000000, "cfigure")
+                ),
+                ("lp2".to_string(), coins(200000, "cfigure")),
+            ],
+            bank_sends(&res)
+        );
+    }
+
+    #[test]
+    fn cancel_without_auto_refund() {
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+
+        let msg = InstantiateMsg {
+            auto_refund: Some(false),
+            ..inst_msg()
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CommitCapital {
+                idempotency_key: None,
+            },
+        )
+        .unwrap();
+
+        // nothing is sent when cancelling
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::Cancel { reason: None };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert!(bank_sends(&res).is_empty());
+
+        // and the call can not be reopened over the unclaimed capital
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, HandleMsg::Reopen {}).unwrap_err();
+        assert_eq!(
+            ContractError::RefundsOutstanding {
+                committed: Coin::new(1000000, "cfigure")
+            },
+            err
+        );
+
+        // claims wait while the contract is paused
+        let can_claim_refund = |deps: Deps| -> bool {
+            let msg = QueryMsg::GetPermissions {
+                address: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+            };
+            let permissions: Permissions =
+                from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+            permissions.can_claim_refund
+        };
+        assert!(can_claim_refund(deps.as_ref()));
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, HandleMsg::Pause {}).unwrap();
+        assert!(!can_claim_refund(deps.as_ref()));
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, HandleMsg::Unpause {}).unwrap();
+
+        // the lp claims its own refund, once
+        let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, HandleMsg::ClaimRefund {}).unwrap();
+        assert_eq!(
+            vec![(
+                "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7".to_string(),
+                coins(1000000, "cfigure")
+            )],
+            bank_sends(&res)
+        );
+        let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, HandleMsg::ClaimRefund {}).unwrap_err();
+        assert_eq!(ContractError::NoRefund {}, err);
+
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, HandleMsg::Reopen {}).unwrap();
+    }
+
+    #[test]
+    fn reopen() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        // only cancelled calls can be reopened
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, HandleMsg::Reopen {}).unwrap_err();
+        assert_eq!(
+            ContractError::WrongStatus {
+                current: Status::PendingCapital,
+                expected: Status::Cancelled,
+            },
+            err
+        );
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CommitCapital {
+                idempotency_key: None,
+            },
+        )
+        .unwrap();
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::Cancel {
+            reason: Some("wrong fund".into()),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // and only by the admin
+        let info = mock_info("creator", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, HandleMsg::Reopen {}).unwrap_err();
+        assert_eq!(
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("creator")
+            },
+            err
+        );
+
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, HandleMsg::Reopen {}).unwrap();
+
+        // the refunded capital has to be committed again
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetState {}).unwrap();
+        let state: State = from_binary(&res).unwrap();
+        assert_eq!(Status::PendingCapital, state.status);
+        assert_eq!(Status::PendingCapital, state.subscriptions[0].status);
+        assert_eq!(Coin::new(0, "cfigure"), state.committed().unwrap());
+        assert_eq!(None, state.cancel_reason);
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CommitCapital {
+                idempotency_key: None,
+            },
+        )
+        .unwrap();
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let _res = reply(deps.as_mut(), mock_env(), capital_reply()).unwrap();
+
+        // called capital can never be reopened
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, HandleMsg::Reopen {}).unwrap_err();
+        assert_eq!(
+            ContractError::WrongStatus {
+                current: Status::CapitalCalled,
+                expected: Status::Cancelled,
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn cancel_with_funds() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+
+        // funds attached to a handler that does not take them would be stuck
+        let info = mock_info("creator", &coins(1000, "cfigure"));
+        let msg = HandleMsg::Cancel { reason: None };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::UnexpectedFunds {
+                denom: "cfigure".into()
+            },
+            err
+        );
+
+        let info = mock_info(
+            "tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000",
+            &coins(1, "nhash"),
+        );
+        let err = execute(deps.as_mut(), mock_env(), info, HandleMsg::Pause {}).unwrap_err();
+        assert_eq!(
+            ContractError::UnexpectedFunds {
+                denom: "nhash".into()
+            },
+            err
+        );
+
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(Status::PendingCapital, state.status);
+        assert!(!state.paused);
+    }
+
+    #[test]
+    fn cancel_with_reason() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+
+        // reasons are kept in state so their length is bounded
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::Cancel {
+            reason: Some("x".repeat(257)),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::ReasonTooLong {
+                length: 257,
+                max: 256
+            },
+            err
+        );
+
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::Cancel {
+            reason: Some("fund closed early".into()),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![
+                attr("action", "cancel"),
+                attr("prior_status", "pending_capital"),
+                attr("status", "cancelled"),
+                attr("sender", "creator"),
+                attr("reason", "fund closed early"),
+            ],
+            res.attributes
+        );
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetState {}).unwrap();
+        let state: State = from_binary(&res).unwrap();
+        assert_eq!(Some("fund closed early".to_string()), state.cancel_reason);
+    }
+
+    #[test]
+    fn draw_capital_guards() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let info = mock_info("creator", &[]);
+        let msg = InstantiateMsg {
+            fee_bps: Some(100),
+            fee_recipient: Some(Addr::unchecked("platform")),
+            ..inst_msg()
+        };
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let committed = config_read(&deps.storage).load().unwrap();
+
+        // drawing 400000 sends on 396000 after the fee and issues 4 shares
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::DrawCapital {
+            capital_amount: Uint128::new(400000),
+            min_capital_out: Some(Uint128::new(400000)),
+            max_shares_out: None,
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::CapitalOutTooLow {
+                capital: Coin::new(396000, "cfigure"),
+                min: Coin::new(400000, "cfigure"),
+            },
+            err
+        );
+        assert_eq!(committed, config_read(&deps.storage).load().unwrap());
+
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::DrawCapital {
+            capital_amount: Uint128::new(400000),
+            min_capital_out: None,
+            max_shares_out: Some(Uint128::new(3)),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::SharesOutTooHigh {
+                shares: Coin::new(4, "fund-coin"),
+                max: Coin::new(3, "fund-coin"),
+            },
+            err
+        );
+        assert_eq!(committed, config_read(&deps.storage).load().unwrap());
+
+        // guards that hold let the draw through
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::DrawCapital {
+            capital_amount: Uint128::new(400000),
+            min_capital_out: Some(Uint128::new(396000)),
+            max_shares_out: Some(Uint128::new(4)),
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(Uint128::new(400000), state.drawn);
+    }
+
+    #[test]
+    fn draw_capital() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // the first draw issues the matching shares and leaves the capital committed
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::DrawCapital {
+            capital_amount: Uint128::new(400000),
+            min_capital_out: None,
+            max_shares_out: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![
+                attr("action", "draw_capital"),
+                attr("prior_status", "capital_committed"),
+                attr("status", "capital_committed"),
+                attr("sender", "creator"),
+                attr("capital", "400000cfigure"),
+                attr("shares", "4fund-coin"),
+                attr("transfer_type", "settlement"),
+            ],
+            res.attributes
+        );
+        assert!(res
+            .messages
+            .iter()
+            .all(|msg| msg.reply_on == ReplyOn::Never));
+        assert_eq!(
+            vec![(
+                "tp18vmzryrvwaeykmdtu6cfrz5sau3dhc5c73ms0u".to_string(),
+                coins(400000, "cfigure")
+            )],
+            bank_sends(&res)
+        );
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetState {}).unwrap();
+        let state: State = from_binary(&res).unwrap();
+        assert_eq!(Status::CapitalCommitted, state.status);
+        assert_eq!(Uint128::new(400000), state.drawn);
+        assert_eq!(
+            Coin::new(1000000, "cfigure"),
+            state.subscriptions[0].committed
+        );
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCommitted {}).unwrap();
+        let committed: Coin = from_binary(&res).unwrap();
+        assert_eq!(Coin::new(1000000, "cfigure"), committed);
+
+        // the rest can only be drawn the same way, and no more than is left
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::PartiallyDrawn {
+                drawn: Coin::new(400000, "cfigure"),
+            },
+            err
+        );
+
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::DrawCapital {
+            capital_amount: Uint128::new(600001),
+            min_capital_out: None,
+            max_shares_out: None,
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::DrawExceedsCommitted {
+                capital: Coin::new(600001, "cfigure"),
+                remaining: Coin::new(600000, "cfigure"),
+            },
+            err
+        );
+
+        // drawing the rest settles the call once the send is confirmed
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::DrawCapital {
+            capital_amount: Uint128::new(600000),
+            min_capital_out: None,
+            max_shares_out: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![
+                attr("action", "draw_capital"),
+                attr("prior_status", "capital_committed"),
+                attr("status", "capital_called"),
+                attr("sender", "creator"),
+                attr("capital", "600000cfigure"),
+                attr("shares", "6fund-coin"),
+                attr("transfer_type", "settlement"),
+            ],
+            res.attributes
+        );
+        let _res = reply(deps.as_mut(), mock_env(), capital_reply()).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetState {}).unwrap();
+        let state: State = from_binary(&res).unwrap();
+        assert_eq!(Status::CapitalCalled, state.status);
+        assert_eq!(Status::CapitalCalled, state.subscriptions[0].status);
+        assert_eq!(
+            Coin::new(1000000, "cfigure"),
+            state.subscriptions[0].committed
+        );
+    }
+
+    #[test]
+    fn reentrancy() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // executing again before the reply, as a callback from the settlement would
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::Cancel { reason: None };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(ContractError::Reentrancy {}, err);
+
+        // the reply releases the contract
+        let _res = reply(deps.as_mut(), mock_env(), capital_reply()).unwrap();
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::Cancel { reason: None };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::AlreadyFinished {
+                status: Status::CapitalCalled,
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn draw_capital_legacy_name() {
+        let msg: HandleMsg =
+            cosmwasm_std::from_slice(br#"{"partial_call":{"capital_amount":"400000"}}"#).unwrap();
+        assert_eq!(
+            HandleMsg::DrawCapital {
+                capital_amount: Uint128::new(400000),
+                min_capital_out: None,
+                max_shares_out: None,
+            },
+            msg
+        );
+    }
+
+    #[test]
+    fn call_capital_to_shares_recipient() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let msg = inst_msg_with(vec![SubscriptionTerms {
+            shares_recipient: Some(Addr::unchecked("custodian")),
+            ..lp_terms()
+        }]);
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // the shares go to the custodian rather than the lp
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let recipient = res
+            .messages
+            .iter()
+            .find_map(|sub_msg| match &sub_msg.msg {
+                CosmosMsg::Custom(ProvenanceMsg {
+                    params:
+                        ProvenanceMsgParams::Marker(MarkerMsgParams::WithdrawCoins {
+                            recipient, ..
+                        }),
+                    ..
+                }) => Some(recipient),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(&Addr::unchecked("custodian"), recipient);
+    }
+
+    #[test]
+    fn commit_capital_after_call() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let msg = inst_msg_with(vec![
+            lp_terms(),
+            SubscriptionTerms {
+                lp_capital_source: Addr::unchecked("lp2"),
+                capital: Coin::new(500000, "cfigure"),
+                shares: Coin::new(5, "fund-coin"),
+                shares_recipient: None,
+            },
+        ]);
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let _res = reply(deps.as_mut(), mock_env(), capital_reply()).unwrap();
+
+        // an lp left out of the call can no longer commit, and the failed commitment keeps its
+        // funds
+        let info = mock_info("lp2", &coins(500000, "cfigure"));
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CommitCapital {
+                idempotency_key: None,
+            },
+        );
+        assert_eq!(
+            ContractError::AlreadyFinished {
+                status: Status::CapitalCalled,
+            },
+            err.unwrap_err()
+        );
+    }
+
+    #[test]
+    fn two_step_close() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1500000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let msg = inst_msg_with(vec![
+            lp_terms(),
+            SubscriptionTerms {
+                lp_capital_source: Addr::unchecked("lp2"),
+                capital: Coin::new(500000, "cfigure"),
+                shares: Coin::new(5, "fund-coin"),
+                shares_recipient: None,
+            },
+        ]);
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CommitCapital {
+                idempotency_key: None,
+            },
+        )
+        .unwrap();
+
+        // nothing to confirm or abort before a close is proposed
+        let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, HandleMsg::ConfirmClose {});
+        assert_eq!(
+            ContractError::WrongStatus {
+                current: Status::CapitalCommitted,
+                expected: Status::ClosePending,
+            },
+            err.unwrap_err()
+        );
+
+        // only those who may call capital can propose
+        let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, HandleMsg::ProposeClose {});
+        assert_eq!(
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+            },
+            err.unwrap_err()
+        );
+
+        let info = mock_info("creator", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, HandleMsg::ProposeClose {}).unwrap();
+        assert_eq!(0, res.messages.len());
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
+        let status: Status = from_binary(&res).unwrap();
+        assert_eq!(Status::ClosePending, status);
+
+        // the close can neither be called over nor committed into
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::WrongStatus {
+                current: Status::ClosePending,
+                expected: Status::CapitalCommitted,
+            },
+            err
+        );
+        let info = mock_info("lp2", &coins(500000, "cfigure"));
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CommitCapital {
+                idempotency_key: None,
+            },
+        );
+        assert_eq!(
+            ContractError::WrongStatus {
+                current: Status::ClosePending,
+                expected: Status::CapitalCommitted,
+            },
+            err.unwrap_err()
+        );
+        let msg = QueryMsg::GetPermissions {
+            address: Addr::unchecked("lp2"),
+        };
+        let permissions: Permissions =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert!(!permissions.can_commit);
+
+        // an lp that would not be called has no say
+        let info = mock_info("lp2", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, HandleMsg::ConfirmClose {});
+        assert_eq!(
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("lp2"),
+            },
+            err.unwrap_err()
+        );
+        let info = mock_info("lp2", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, HandleMsg::AbortClose {});
+        assert_eq!(
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("lp2"),
+            },
+            err.unwrap_err()
+        );
+
+        // the committed lp aborts back to committed, and the proposal starts over
+        let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, HandleMsg::AbortClose {}).unwrap();
+        assert_eq!(0, res.messages.len());
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(Status::CapitalCommitted, state.status);
+
+        let info = mock_info("lp2", &coins(500000, "cfigure"));
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CommitCapital {
+                idempotency_key: None,
+            },
+        )
+        .unwrap();
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, HandleMsg::ProposeClose {}).unwrap();
+
+        // one confirmation keeps it pending
+        let info = mock_info("lp2", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, HandleMsg::ConfirmClose {}).unwrap();
+        assert_eq!(0, res.messages.len());
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(Status::ClosePending, state.status);
+
+        // confirming again changes nothing, so it is refused
+        let info = mock_info("lp2", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, HandleMsg::ConfirmClose {});
+        assert_eq!(
+            ContractError::AlreadyConfirmed {
+                lp_capital_source: Addr::unchecked("lp2"),
+            },
+            err.unwrap_err()
+        );
+
+        // the last one settles every committed subscription
+        let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, HandleMsg::ConfirmClose {}).unwrap();
+        assert_eq!(
+            vec![(
+                "tp18vmzryrvwaeykmdtu6cfrz5sau3dhc5c73ms0u".to_string(),
+                coins(1500000, "cfigure")
+            )],
+            bank_sends(&res)
+        );
+        assert_eq!(
+            Some(&attr("action", "confirm_close")),
+            res.attributes.first()
+        );
+
+        let _res = reply(deps.as_mut(), mock_env(), capital_reply()).unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(Status::CapitalCalled, state.status);
+    }
+
+    #[test]
+    fn call_capital_insufficient_funds() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(600000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // the contract holds less than was recorded as committed
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::InsufficientFunds {
+                held: Coin::new(600000, "cfigure"),
+                required: Coin::new(1000000, "cfigure"),
+            },
+            err
+        );
+
+        // nothing was settled
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
+        let status: Status = from_binary(&res).unwrap();
+        assert_eq!(Status::CapitalCommitted, status);
+    }
+
+    #[test]
+    fn observers_notified_on_close() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let mut msg = inst_msg();
+        msg.observers = vec![Addr::unchecked("observer1"), Addr::unchecked("observer2")];
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CommitCapital {
+                idempotency_key: None,
+            },
+        )
+        .unwrap();
+
+        let notifications = |status: Status| {
+            ["observer1", "observer2"]
+                .iter()
+                .map(|observer| WasmMsg::Execute {
+                    contract_addr: observer.to_string(),
+                    msg: to_binary(&ObserverMsg::StatusChanged {
+                        id: "capital-call-1".into(),
+                        status: status.clone(),
+                    })
+                    .unwrap(),
+                    funds: vec![],
+                })
+                .collect::<Vec<_>>()
+        };
+
+        fn wasm_msgs<T: Clone + std::fmt::Debug + PartialEq + schemars::JsonSchema>(
+            res: &Response<T>,
+        ) -> Vec<WasmMsg> {
+            res.messages
+                .iter()
+                .filter_map(|sub_msg| match &sub_msg.msg {
+                    CosmosMsg::Wasm(msg) => Some(msg.clone()),
+                    _ => None,
+                })
+                .collect()
+        }
+
+        // cancelling notifies every observer
+        let mut cancel_deps = mock_dependencies(&coins(1000000, "cfigure"));
+        cancel_deps.storage.set(
+            &config_raw_key(),
+            &deps.storage.get(&config_raw_key()).unwrap(),
+        );
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::Cancel { reason: None };
+        let res = execute(cancel_deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(notifications(Status::Cancelled), wasm_msgs(&res));
+
+        // nothing is sent until the call is final
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert!(wasm_msgs(&res).is_empty());
+
+        let res = reply(deps.as_mut(), mock_env(), capital_reply()).unwrap();
+        assert_eq!(notifications(Status::CapitalCalled), wasm_msgs(&res));
+    }
+
+    #[test]
+    fn registry_notified() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {
+            registry: Some(Addr::unchecked("registry")),
+            ..inst_msg()
+        };
+        let info = mock_info("creator", &[]);
+        let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![SubMsg::new(WasmMsg::Execute {
+                contract_addr: "registry".into(),
+                msg: to_binary(&RegistryMsg::Register {
+                    id: "capital-call-1".into(),
+                    contract_address: Addr::unchecked(MOCK_CONTRACT_ADDR),
+                    status: Status::PendingCapital,
+                })
+                .unwrap(),
+                funds: vec![],
+            })],
+            res.messages
+        );
+
+        // executions leaving the status alone are not reported
+        let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, HandleMsg::AcceptTerms {}).unwrap();
+        assert!(res.messages.is_empty());
+
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::Cancel { reason: None };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![SubMsg::reply_on_error(
+                WasmMsg::Execute {
+                    contract_addr: "registry".into(),
+                    msg: to_binary(&RegistryMsg::UpdateStatus {
+                        id: "capital-call-1".into(),
+                        status: Status::Cancelled,
+                    })
+                    .unwrap(),
+                    funds: vec![],
+                },
+                NOTIFY_REPLY_ID
+            )],
+            res.messages
+        );
+    }
+
+    #[test]
+    fn failing_observer_does_not_block_close() {
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+
+        let msg = InstantiateMsg {
+            observers: vec![Addr::unchecked("observer1")],
+            registry: Some(Addr::unchecked("registry")),
+            ..inst_msg()
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // every notification only replies if it fails
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::Cancel { reason: None };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let notifications: Vec<&SubMsg<ProvenanceMsg>> = res
+            .messages
+            .iter()
+            .filter(|sub_msg| matches!(sub_msg.msg, CosmosMsg::Wasm(_)))
+            .collect();
+        assert_eq!(2, notifications.len());
+        assert!(notifications
+            .iter()
+            .all(|sub_msg| sub_msg.reply_on == ReplyOn::Error && sub_msg.id == NOTIFY_REPLY_ID));
+
+        // and the failure is swallowed, leaving the cancel and its refund standing
+        let failure = Reply {
+            id: NOTIFY_REPLY_ID,
+            result: ContractResult::Err("no such contract: observer1".into()),
+        };
+        let res = reply(deps.as_mut(), mock_env(), failure).unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(
+            vec![
+                attr("action", "notify_failed"),
+                attr("error", "no such contract: observer1"),
+            ],
+            res.attributes
+        );
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
+        let status: Status = from_binary(&res).unwrap();
+        assert_eq!(Status::Cancelled, status);
+    }
+
+    #[test]
+    fn call_capital() {
+        // Create a mock querier with our expected marker.
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker.clone()]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        // lp can commit capital
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // gp can call capital
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![
+                attr("action", "call_capital"),
+                attr("prior_status", "capital_committed"),
+                attr("status", "capital_called"),
+                attr("sender", "creator"),
+                attr("capital", "1000000cfigure"),
+                attr("shares", "10fund-coin"),
+                attr("transfer_type", "settlement"),
+            ],
+            _res.attributes
+        );
+
+        let mint = _res
+            .messages
+            .iter()
+            .find_map(|sub_msg| match &sub_msg.msg {
+                CosmosMsg::Custom(ProvenanceMsg {
+                    params: ProvenanceMsgParams::Marker(MarkerMsgParams::MintMarkerSupply { coin }),
+                    ..
+                }) => Some(coin),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(10, u128::from(mint.amount));
+
+        let (withdraw_coin, withdraw_recipient) = _res
+            .messages
+            .iter()
+            .find_map(|sub_msg| match &sub_msg.msg {
+                CosmosMsg::Custom(ProvenanceMsg {
+                    params:
+                        ProvenanceMsgParams::Marker(MarkerMsgParams::WithdrawCoins {
+                            coin,
+                            recipient,
+                            ..
+                        }),
+                    ..
+                }) => Some((coin, recipient)),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(10, u128::from(withdraw_coin.amount));
+        assert_eq!(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            withdraw_recipient.to_string()
+        );
+
+        let (to_address, amount) = _res
+            .messages
+            .iter()
+            .find_map(|sub_msg| match &sub_msg.msg {
+                CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => Some((to_address, amount)),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!("tp18vmzryrvwaeykmdtu6cfrz5sau3dhc5c73ms0u", to_address);
+        assert_eq!(1000000, u128::from(amount[0].amount));
+        assert_eq!("cfigure", amount[0].denom);
+
+        // the capital send must be confirmed before the call is final
+        let send = _res
+            .messages
+            .iter()
+            .find(|sub_msg| matches!(sub_msg.msg, CosmosMsg::Bank(_)))
+            .unwrap();
+        assert_eq!(CALL_CAPITAL_REPLY_ID, send.id);
+        assert_eq!(ReplyOn::Success, send.reply_on);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
+        let status: Status = from_binary(&res).unwrap();
+        assert_eq!(Status::CapitalCommitted, status);
+
+        let res = reply(deps.as_mut(), mock_env(), capital_reply()).unwrap();
+        assert_eq!(
+            vec![
+                attr("action", "confirm_call_capital"),
+                attr("prior_status", "capital_committed"),
+                attr("status", "capital_called"),
+                attr("sender", "creator"),
+            ],
+            res.attributes
+        );
+
+        // should be in capital called state
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
+        let status: Status = from_binary(&res).unwrap();
+        assert_eq!(Status::CapitalCalled, status);
+
+        // replies for anything else are rejected
+        let err = reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: 7,
+                ..capital_reply()
+            },
+        )
+        .unwrap_err();
+        assert_eq!(ContractError::UnknownReply { id: 7 }, err);
+    }
+
+    #[test]
+    fn call_capital_status() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        // nothing can be called before capital is committed
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::WrongStatus {
+                current: Status::PendingCapital,
+                expected: Status::CapitalCommitted,
+            },
+            err
+        );
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let _res = reply(deps.as_mut(), mock_env(), capital_reply()).unwrap();
+
+        // a retried call reports that it is already done
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::AlreadyFinished {
+                status: Status::CapitalCalled
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn call_capital_cutback() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        // 1000000 capital for 7 shares does not divide evenly
+        let msg = inst_msg_with(vec![SubscriptionTerms {
+            shares: Coin::new(7, "fund-coin"),
+            ..lp_terms()
+        }]);
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // gp can not issue more shares than agreed
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital {
+            allocations: Some(vec![Allocation {
+                lp_capital_source: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+                shares: Uint128::new(8),
+            }]),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::WrongShares {
+                got: Coin::new(8, "fund-coin"),
+                expected: Coin::new(7, "fund-coin"),
+            },
+            err
+        );
+
+        // gp can issue fewer shares, calling capital rounded down in favor of the lp
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital {
+            allocations: Some(vec![Allocation {
+                lp_capital_source: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+                shares: Uint128::new(3),
+            }]),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![
+                (
+                    "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7".to_string(),
+                    coins(571429, "cfigure")
+                ),
+                (
+                    "tp18vmzryrvwaeykmdtu6cfrz5sau3dhc5c73ms0u".to_string(),
+                    coins(428571, "cfigure")
+                ),
+            ],
+            bank_sends(&res)
+        );
+        assert!(res.attributes.contains(&attr("shares", "3fund-coin")));
+        assert!(res.attributes.contains(&attr("refund", "571429cfigure")));
+    }
+
+    #[test]
+    fn multiple_subscriptions() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1200000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let msg = inst_msg_with(vec![
+            lp_terms(),
+            SubscriptionTerms {
+                lp_capital_source: Addr::unchecked("lp2"),
+                capital: Coin::new(500000, "cfigure"),
+                shares: Coin::new(5, "fund-coin"),
+                shares_recipient: None,
+            },
+        ]);
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        // second lp commits part of its capital
+        let info = mock_info("lp2", &coins(200000, "cfigure"));
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
+        let status: Status = from_binary(&res).unwrap();
+        assert_eq!(Status::PendingCapital, status);
+
+        // first lp commits in full, which is enough to call capital
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
+        let status: Status = from_binary(&res).unwrap();
+        assert_eq!(Status::CapitalCommitted, status);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCommitted {}).unwrap();
+        let committed: Coin = from_binary(&res).unwrap();
+        assert_eq!(Coin::new(1200000, "cfigure"), committed);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSubscriptions {}).unwrap();
+        let subscriptions: Vec<Subscription> = from_binary(&res).unwrap();
+        assert_eq!(Status::CapitalCommitted, subscriptions[0].status);
+        assert_eq!(Status::PendingCapital, subscriptions[1].status);
+        assert_eq!(Coin::new(200000, "cfigure"), subscriptions[1].committed);
+
+        // only the committed lp is settled, the partial commitment is refunded
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![
+                ("lp2".to_string(), coins(200000, "cfigure")),
+                (
+                    "tp18vmzryrvwaeykmdtu6cfrz5sau3dhc5c73ms0u".to_string(),
+                    coins(1000000, "cfigure")
+                ),
+            ],
+            bank_sends(&res)
+        );
+        assert!(res.attributes.contains(&attr("shares", "10fund-coin")));
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSubscriptions {}).unwrap();
+        let subscriptions: Vec<Subscription> = from_binary(&res).unwrap();
+        assert_eq!(Status::CapitalCalled, subscriptions[0].status);
+        assert_eq!(Status::Cancelled, subscriptions[1].status);
+    }
+
+    #[test]
+    fn call_capital_to_recipient() {
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+
+        let msg = InstantiateMsg {
+            capital_recipient: Some(Addr::unchecked("custody")),
+            ..inst_msg()
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // capital settles to the recipient rather than the marker
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![("custody".to_string(), coins(1000000, "cfigure"))],
+            bank_sends(&res)
+        );
+    }
+
+    #[test]
+    fn call_capital_message_order() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1200000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let msg = InstantiateMsg {
+            fee_bps: Some(250),
+            fee_recipient: Some(Addr::unchecked("platform")),
+            settlement_attribute: Some("settlement.capital-call".into()),
+            ..inst_msg_with(vec![
+                lp_terms(),
+                SubscriptionTerms {
+                    lp_capital_source: Addr::unchecked("lp2"),
+                    capital: Coin::new(500000, "cfigure"),
+                    shares: Coin::new(5, "fund-coin"),
+                    shares_recipient: None,
+                },
+            ])
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CommitCapital {
+                idempotency_key: None,
+            },
+        )
+        .unwrap();
+        let info = mock_info("lp2", &coins(200000, "cfigure"));
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CommitCapital {
+                idempotency_key: None,
+            },
+        )
+        .unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // refunds, then shares, then the fee, with the settlement confirmed in reply last
+        assert_eq!(
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "lp2".into(),
+                    amount: coins(200000, "cfigure"),
+                }),
+                SubMsg::new(mint_marker_supply(10, "fund-coin").unwrap()),
+                SubMsg::new(
+                    withdraw_coins(
+                        "fund-coin",
+                        10,
+                        "fund-coin",
+                        Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+                    )
+                    .unwrap()
+                ),
+                SubMsg::new(
+                    add_json_attribute(
+                        Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+                        "settlement.capital-call",
+                        &SettlementAttribute {
+                            gp: Addr::unchecked("creator"),
+                            capital: Coin::new(1000000, "cfigure"),
+                            shares: Coin::new(10, "fund-coin"),
+                        },
+                    )
+                    .unwrap()
+                ),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "platform".into(),
+                    amount: coins(25000, "cfigure"),
+                }),
+                SubMsg::reply_on_success(
+                    BankMsg::Send {
+                        to_address: "tp18vmzryrvwaeykmdtu6cfrz5sau3dhc5c73ms0u".into(),
+                        amount: coins(975000, "cfigure"),
+                    },
+                    CALL_CAPITAL_REPLY_ID
+                ),
+            ],
+            res.messages
+        );
+        let transfer_types: Vec<_> = res
+            .attributes
+            .iter()
+            .filter(|attr| attr.key == "transfer_type")
+            .map(|attr| attr.value.as_str())
+            .collect();
+        assert_eq!(vec!["refund", "fee", "settlement"], transfer_types);
+    }
+
+    #[test]
+    fn close_message_count() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1200000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let msg = InstantiateMsg {
+            fee_bps: Some(250),
+            fee_recipient: Some(Addr::unchecked("platform")),
+            settlement_attribute: Some("settlement.capital-call".into()),
+            observers: vec![Addr::unchecked("observer1"), Addr::unchecked("observer2")],
+            registry: Some(Addr::unchecked("registry")),
+            ..inst_msg_with(vec![
+                lp_terms(),
+                SubscriptionTerms {
+                    lp_capital_source: Addr::unchecked("lp2"),
+                    capital: Coin::new(500000, "cfigure"),
+                    shares: Coin::new(5, "fund-coin"),
+                    shares_recipient: None,
+                },
+            ])
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        // nothing can be counted before capital is committed
+        let msg = QueryMsg::GetCloseMessageCount {};
+        let _err = query(deps.as_ref(), mock_env(), msg).unwrap_err();
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CommitCapital {
+                idempotency_key: None,
+            },
+        )
+        .unwrap();
+        let info = mock_info("lp2", &coins(200000, "cfigure"));
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CommitCapital {
+                idempotency_key: None,
+            },
+        )
+        .unwrap();
+
+        let msg = QueryMsg::GetCloseMessageCount {};
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let count: u32 = from_binary(&res).unwrap();
+
+        // a refund, the mint, a withdrawal, a settlement attribute, the fee and the settlement,
+        // then both observers and the registry once the reply settles the call
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let reply_res = reply(deps.as_mut(), mock_env(), capital_reply()).unwrap();
+        assert_eq!(9, count);
+        assert_eq!(
+            res.messages.len() + reply_res.messages.len(),
+            count as usize
+        );
+    }
+
+    #[test]
+    fn simulate_call() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let msg = InstantiateMsg {
+            fee_bps: Some(250),
+            fee_recipient: Some(Addr::unchecked("platform")),
+            ..inst_msg()
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        // nothing can be simulated before capital is committed
+        let msg = QueryMsg::SimulateCall { allocations: None };
+        let _err = query(deps.as_ref(), mock_env(), msg).unwrap_err();
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CommitCapital {
+                idempotency_key: None,
+            },
+        )
+        .unwrap();
+
+        let allocations = Some(vec![Allocation {
+            lp_capital_source: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+            shares: Uint128::from(3u128),
+        }]);
+        let msg = QueryMsg::SimulateCall {
+            allocations: allocations.clone(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let transfers: Vec<Transfer> = from_binary(&res).unwrap();
+        let transfer = |transfer_type: &str, recipient: &str, coin: Coin| Transfer {
+            transfer_type: transfer_type.into(),
+            recipient: Addr::unchecked(recipient),
+            coin,
+        };
+        assert_eq!(
+            vec![
+                transfer(
+                    "refund",
+                    "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+                    Coin::new(700000, "cfigure")
+                ),
+                transfer(
+                    "shares",
+                    "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+                    Coin::new(3, "fund-coin")
+                ),
+                transfer("fee", "platform", Coin::new(7500, "cfigure")),
+                transfer(
+                    "settlement",
+                    "tp18vmzryrvwaeykmdtu6cfrz5sau3dhc5c73ms0u",
+                    Coin::new(292500, "cfigure")
+                ),
+            ],
+            transfers
+        );
+
+        // the simulation leaves the state alone and matches the actual call
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
+        let status: Status = from_binary(&res).unwrap();
+        assert_eq!(Status::CapitalCommitted, status);
+
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        // in the order the call sends them
+        let sent: Vec<(String, Coin)> = res
+            .messages
+            .iter()
+            .filter_map(|sub_msg| match &sub_msg.msg {
+                CosmosMsg::Custom(ProvenanceMsg {
+                    params:
+                        ProvenanceMsgParams::Marker(MarkerMsgParams::WithdrawCoins {
+                            coin,
+                            recipient,
+                            ..
+                        }),
+                    ..
+                }) => Some((recipient.to_string(), coin.clone())),
+                CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+                    Some((to_address.clone(), amount[0].clone()))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            transfers
+                .into_iter()
+                .map(|transfer| (transfer.recipient.to_string(), transfer.coin))
+                .collect::<Vec<_>>(),
+            sent
+        );
+    }
+
+    #[test]
+    fn call_capital_by_delegate() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let msg = InstantiateMsg {
+            authorized_closers: vec![Addr::unchecked("agent")],
+            ..inst_msg()
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // addresses that are not listed still can not call capital
+        let info = mock_info("stranger", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("stranger")
+            },
+            err
+        );
+
+        // a listed delegate can, and the capital settles to the marker as usual
+        let info = mock_info("agent", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![(
+                "tp18vmzryrvwaeykmdtu6cfrz5sau3dhc5c73ms0u".to_string(),
+                coins(1000000, "cfigure")
+            )],
+            bank_sends(&res)
+        );
+    }
+
+    #[test]
+    fn call_capital_by_granted_closer() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let grant = |address: &str, role| HandleMsg::GrantRole {
+            address: Addr::unchecked(address),
+            role,
+        };
+
+        // only admins grant roles, and never to an lp
+        let info = mock_info("creator", &[]);
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            grant("agent", Role::Closer),
+        );
+        assert_eq!(
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("creator")
+            },
+            err.unwrap_err()
+        );
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = grant("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", Role::Closer);
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::DuplicateRole {
+                address: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7")
+            },
+            err
+        );
+
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            grant("agent", Role::Closer),
+        )
+        .unwrap();
+        assert_eq!(
+            vec![attr("address", "agent"), attr("role", "closer")],
+            res.attributes[4..].to_vec()
+        );
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            grant("agent", Role::Closer),
+        );
+        assert_eq!(
+            ContractError::RoleAlreadyGranted {
+                address: Addr::unchecked("agent"),
+                role: Role::Closer,
+            },
+            err.unwrap_err()
+        );
+
+        // a closer can not cancel, and loses the call once revoked
+        let info = mock_info("agent", &[]);
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::Cancel { reason: None },
+        );
+        assert_eq!(
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("agent")
+            },
+            err.unwrap_err()
+        );
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::RevokeRole {
+            address: Addr::unchecked("agent"),
+            role: Role::Closer,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap();
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::RoleNotGranted {
+                address: Addr::unchecked("agent"),
+                role: Role::Closer,
+            },
+            err
+        );
+        let info = mock_info("agent", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("agent")
+            },
+            err
+        );
+
+        // granted again, the closer calls capital like the gp
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            grant("agent", Role::Closer),
+        )
+        .unwrap();
+        let info = mock_info("agent", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![(
+                "tp18vmzryrvwaeykmdtu6cfrz5sau3dhc5c73ms0u".to_string(),
+                coins(1000000, "cfigure")
+            )],
+            bank_sends(&res)
+        );
+    }
+
+    #[test]
+    fn update_subscription() {
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        let update = |new_lp_capital_source: &str| HandleMsg::UpdateSubscription {
+            lp_capital_source: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+            new_lp_capital_source: Addr::unchecked(new_lp_capital_source),
+        };
+
+        // only the admin can correct a subscription
+        let info = mock_info("creator", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, update("lp2")).unwrap_err();
+        assert_eq!(
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("creator")
+            },
+            err
+        );
+
+        // the new lp can not already hold another role
+        let admin = || mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let err = execute(deps.as_mut(), mock_env(), admin(), update("creator")).unwrap_err();
+        assert_eq!(
+            ContractError::DuplicateRole {
+                address: Addr::unchecked("creator")
+            },
+            err
+        );
+
+        let res = execute(deps.as_mut(), mock_env(), admin(), update("lp2")).unwrap();
+        assert_eq!(
+            vec![
+                attr("action", "update_subscription"),
+                attr("prior_status", "pending_capital"),
+                attr("status", "pending_capital"),
+                attr("sender", "tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000"),
+                attr(
+                    "prior_lp_capital_source",
+                    "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"
+                ),
+                attr("lp_capital_source", "lp2"),
+            ],
+            res.attributes
+        );
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(
+            Addr::unchecked("lp2"),
+            state.subscriptions[0].lp_capital_source
+        );
+        assert!(!state.subscriptions[0].accepted);
+
+        // once committed the subscription is fixed
+        accept_terms(deps.as_mut());
+        let info = mock_info("lp2", &coins(1000000, "cfigure"));
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CommitCapital {
+                idempotency_key: None,
+            },
+        )
+        .unwrap();
+        let msg = HandleMsg::UpdateSubscription {
+            lp_capital_source: Addr::unchecked("lp2"),
+            new_lp_capital_source: Addr::unchecked("lp3"),
+        };
+        let err = execute(deps.as_mut(), mock_env(), admin(), msg).unwrap_err();
+        assert_eq!(
+            ContractError::WrongStatus {
+                current: Status::CapitalCommitted,
+                expected: Status::PendingCapital
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn update_shares_amount() {
+        let mut deps = mock_dependencies(&[]);
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        let update = |shares: Coin| HandleMsg::UpdateSharesAmount {
+            lp_capital_source: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+            shares,
+        };
+
+        // shares are only repriced once capital is committed
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = update(Coin::new(12, "fund-coin"));
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::WrongStatus {
+                current: Status::PendingCapital,
+                expected: Status::CapitalCommitted,
+            },
+            err
+        );
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CommitCapital {
+                idempotency_key: None,
+            },
+        )
+        .unwrap();
+
+        // only by the admin, and never into another denom
+        let info = mock_info("creator", &[]);
+        let msg = update(Coin::new(12, "fund-coin"));
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("creator")
+            },
+            err
+        );
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = update(Coin::new(12, "other-coin"));
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::WrongDenom {
+                got: "other-coin".into(),
+                expected: "fund-coin".into(),
+            },
+            err
+        );
+
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = update(Coin::new(12, "fund-coin"));
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![
+                attr(
+                    "lp_capital_source",
+                    "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"
+                ),
+                attr("prior_shares", "10fund-coin"),
+                attr("shares", "12fund-coin"),
+            ],
+            res.attributes[4..].to_vec()
+        );
+
+        // the capital committed is untouched
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(Coin::new(12, "fund-coin"), state.subscriptions[0].shares);
+        assert_eq!(
+            Coin::new(1000000, "cfigure"),
+            state.subscriptions[0].committed
+        );
+    }
+
+    #[test]
+    fn call_capital_too_soon() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let msg = InstantiateMsg {
+            min_commit_duration: Some(3600),
+            ..inst_msg()
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), env_at(1500000000), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), env_at(1500000000), info, msg).unwrap();
+
+        let can_call = |deps: Deps, seconds: u64| -> bool {
+            let msg = QueryMsg::GetPermissions {
+                address: Addr::unchecked("creator"),
+            };
+            let permissions: Permissions =
+                from_binary(&query(deps, env_at(seconds), msg).unwrap()).unwrap();
+            permissions.can_call
+        };
+
+        // neither in the same block nor a second before the minimum
+        for seconds in [1500000000, 1500003599] {
+            assert!(!can_call(deps.as_ref(), seconds));
+            let info = mock_info("creator", &[]);
+            let msg = HandleMsg::CallCapital { allocations: None };
+            let err = execute(deps.as_mut(), env_at(seconds), info, msg).unwrap_err();
+            assert_eq!(
+                ContractError::TooSoon {
+                    earliest: "1500003600".into()
+                },
+                err
+            );
+        }
+
+        assert!(can_call(deps.as_ref(), 1500003600));
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let _res = execute(deps.as_mut(), env_at(1500003600), info, msg).unwrap();
+    }
+
+    #[test]
+    fn call_capital_too_soon_far_out() {
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+
+        let msg = InstantiateMsg {
+            min_commit_duration: Some(u64::MAX / 2),
+            ..inst_msg()
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), env_at(1500000000), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), env_at(1500000000), info, msg).unwrap();
+
+        // a minimum past the latest representable time is simply never reached
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let err = execute(deps.as_mut(), env_at(1500003600), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::TooSoon {
+                earliest: "18446744073".into()
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn call_capital_fee() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        // fees are bounded by the capital and need somewhere to go
+        for (fee_bps, fee_recipient) in [(10001, Some(Addr::unchecked("platform"))), (250, None)] {
+            let msg = InstantiateMsg {
+                fee_bps: Some(fee_bps),
+                fee_recipient,
+                ..inst_msg()
+            };
+            let info = mock_info("creator", &[]);
+            let err = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+            assert_eq!(ContractError::InvalidFee { fee_bps }, err);
+        }
+
+        let msg = InstantiateMsg {
+            fee_bps: Some(250),
+            fee_recipient: Some(Addr::unchecked("platform")),
+            ..inst_msg()
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // 2.5% of the capital goes to the fee recipient, the rest to the marker
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![
+                ("platform".to_string(), coins(25000, "cfigure")),
+                (
+                    "tp18vmzryrvwaeykmdtu6cfrz5sau3dhc5c73ms0u".to_string(),
+                    coins(975000, "cfigure")
+                ),
+            ],
+            bank_sends(&res)
+        );
+        assert_eq!(
+            vec![
+                attr("fee", "25000cfigure"),
+                attr("transfer_type", "fee"),
+                attr("transfer_type", "settlement"),
+            ],
+            res.attributes[6..]
+        );
+    }
+
+    #[test]
+    fn call_capital_shares_fee() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1500000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let msg = InstantiateMsg {
+            fee_bps: Some(1500),
+            fee_basis: Some(FeeBasis::Shares),
+            fee_recipient: Some(Addr::unchecked("platform")),
+            ..inst_msg_with(vec![
+                lp_terms(),
+                SubscriptionTerms {
+                    lp_capital_source: Addr::unchecked("lp2"),
+                    capital: Coin::new(500000, "cfigure"),
+                    shares: Coin::new(5, "fund-coin"),
+                    shares_recipient: None,
+                },
+            ])
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CommitCapital {
+                idempotency_key: None,
+            },
+        )
+        .unwrap();
+        let info = mock_info("lp2", &coins(500000, "cfigure"));
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CommitCapital {
+                idempotency_key: None,
+            },
+        )
+        .unwrap();
+
+        // the capital goes to the marker whole
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![(
+                "tp18vmzryrvwaeykmdtu6cfrz5sau3dhc5c73ms0u".to_string(),
+                coins(1500000, "cfigure")
+            )],
+            bank_sends(&res)
+        );
+
+        // 15% of each lp's shares rounds down to 1 and 0, which the fee recipient gets instead
+        let withdrawals: Vec<(String, Coin)> = res
+            .messages
+            .iter()
+            .filter_map(|sub_msg| match &sub_msg.msg {
+                CosmosMsg::Custom(ProvenanceMsg {
+                    params:
+                        ProvenanceMsgParams::Marker(MarkerMsgParams::WithdrawCoins {
+                            coin,
+                            recipient,
+                            ..
+                        }),
+                    ..
+                }) => Some((recipient.to_string(), coin.clone())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7".to_string(),
+                    Coin::new(9, "fund-coin")
+                ),
+                ("lp2".to_string(), Coin::new(5, "fund-coin")),
+                ("platform".to_string(), Coin::new(1, "fund-coin")),
+            ],
+            withdrawals
+        );
+        assert!(res.attributes.contains(&attr("shares", "15fund-coin")));
+        assert!(res.attributes.contains(&attr("fee", "1fund-coin")));
+    }
+
+    #[test]
+    fn call_capital_settlement_attribute() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let msg = InstantiateMsg {
+            settlement_attribute: Some("capital-call.fund.pb".into()),
+            ..inst_msg()
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // the lp should be tagged with what it settled
+        let (address, name, value) = res
+            .messages
+            .iter()
+            .find_map(|sub_msg| match &sub_msg.msg {
+                CosmosMsg::Custom(ProvenanceMsg {
+                    params:
+                        ProvenanceMsgParams::Attribute(AttributeMsgParams::AddAttribute {
+                            address,
+                            name,
+                            value,
+                            ..
+                        }),
+                    ..
+                }) => Some((address, name, value)),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            address.as_str()
+        );
+        assert_eq!("capital-call.fund.pb", name);
+        let settlement: SettlementAttribute = from_binary(value).unwrap();
+        assert_eq!(
+            SettlementAttribute {
+                gp: Addr::unchecked("creator"),
+                capital: Coin::new(1000000, "cfigure"),
+                shares: Coin::new(10, "fund-coin"),
+            },
+            settlement
+        );
+    }
+
+    #[test]
+    fn history() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+
+        let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &[]);
+        let msg = HandleMsg::AcceptTerms {};
+        let _res = execute(deps.as_mut(), env_at(1589999999), info, msg).unwrap();
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), env_at(1590000000), info, msg).unwrap();
+
+        // failed executions are not recorded
+        let info = mock_info("stranger", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let _err = execute(deps.as_mut(), env_at(1590000001), info, msg).unwrap_err();
+
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let _res = execute(deps.as_mut(), env_at(1590000002), info, msg).unwrap();
+
+        // the call is only recorded once its capital send is confirmed
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetHistory {}).unwrap();
+        let history: Vec<TransitionRecord> = from_binary(&res).unwrap();
+        assert_eq!(2, history.len());
+        let _res = reply(deps.as_mut(), env_at(1590000002), capital_reply()).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetHistory {}).unwrap();
+        let history: Vec<TransitionRecord> = from_binary(&res).unwrap();
+        assert_eq!(
+            vec![
+                TransitionRecord {
+                    status: Status::PendingCapital,
+                    timestamp: Timestamp::from_seconds(1589999999),
+                    sender: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+                },
+                TransitionRecord {
+                    status: Status::CapitalCommitted,
+                    timestamp: Timestamp::from_seconds(1590000000),
+                    sender: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+                },
+                TransitionRecord {
+                    status: Status::CapitalCalled,
+                    timestamp: Timestamp::from_seconds(1590000002),
+                    sender: Addr::unchecked("creator"),
+                },
+            ],
+            history
+        );
+    }
+
+    #[test]
+    fn audit_trail() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), env_at(1589999998), info, inst_msg()).unwrap();
+
+        let hash = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::AttachDocument {
+            hash: hash.into(),
+            uri: None,
+        };
+        let _res = execute(deps.as_mut(), env_at(1589999998), info, msg).unwrap();
+        let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &[]);
+        let msg = HandleMsg::AcceptTerms {};
+        let _res = execute(deps.as_mut(), env_at(1589999999), info, msg).unwrap();
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), env_at(1590000000), info, msg).unwrap();
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let _res = execute(deps.as_mut(), env_at(1590000001), info, msg).unwrap();
+        let _res = reply(deps.as_mut(), env_at(1590000001), capital_reply()).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAuditTrail {}).unwrap();
+        let audit: AuditTrail = from_binary(&res).unwrap();
+        assert_eq!("capital-call-1", audit.id);
+        assert_eq!(Addr::unchecked("creator"), audit.gp);
+        assert_eq!(
+            Addr::unchecked("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000"),
+            audit.admin
+        );
+        assert_eq!(
+            vec![
+                Addr::unchecked("tp18vmzryrvwaeykmdtu6cfrz5sau3dhc5c73ms0u"),
+                Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+            ],
+            audit.parties.recipients
+        );
+        assert_eq!(
+            vec![
+                Status::PendingCapital,
+                Status::PendingCapital,
+                Status::CapitalCommitted,
+                Status::CapitalCalled
+            ],
+            audit
+                .history
+                .into_iter()
+                .map(|record| record.status)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![DocumentRef {
+                hash: hash.into(),
+                uri: None,
+                timestamp: Timestamp::from_seconds(1589999998),
+                sender: Addr::unchecked("creator"),
+            }],
+            audit.documents
+        );
+    }
+
+    #[test]
+    fn available_actions() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+
+        let available = |deps: Deps| -> Vec<String> {
+            let res = query(deps, mock_env(), QueryMsg::GetAvailableActions {}).unwrap();
+            from_binary(&res).unwrap()
+        };
+        let always = ["attach_document", "grant_role", "revoke_role"];
+        let expected = |actions: &[&str]| -> Vec<String> {
+            let mut expected = vec!["pause", "update_admin", "emergency_withdraw"];
+            expected.extend(actions);
+            expected.extend(always);
+            expected.into_iter().map(String::from).collect()
+        };
+
+        assert_eq!(
+            expected(&[
+                "cancel",
+                "accept_terms",
+                "commit_capital",
+                "commit_capital_from",
+                "update_terms",
+                "update_subscription",
+                "extend_due_date",
+            ]),
+            available(deps.as_ref())
+        );
+
+        // only the pause exempt handlers are left while paused
+        let admin = || mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), admin(), HandleMsg::Pause {}).unwrap();
+        assert_eq!(
+            vec!["unpause", "update_admin", "emergency_withdraw"],
+            available(deps.as_ref())
+        );
+        let _res = execute(deps.as_mut(), mock_env(), admin(), HandleMsg::Unpause {}).unwrap();
+
+        accept_terms(deps.as_mut());
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let _res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CommitCapital {
+                idempotency_key: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            expected(&[
+                "cancel",
+                "withdraw_expired",
+                "draw_capital",
+                "call_capital",
+                "propose_close",
+                "update_shares_amount",
+                "extend_due_date",
+            ]),
+            available(deps.as_ref())
+        );
+
+        let info = mock_info("creator", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, HandleMsg::ProposeClose {}).unwrap();
+        assert_eq!(
+            expected(&[
+                "cancel",
+                "withdraw_expired",
+                "confirm_close",
+                "abort_close",
+                "extend_due_date",
+            ]),
+            available(deps.as_ref())
+        );
+
+        // confirming settles the call, after which only documents and roles remain
+        let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, HandleMsg::ConfirmClose {}).unwrap();
+        let _res = reply(deps.as_mut(), mock_env(), capital_reply()).unwrap();
+        assert_eq!(
+            vec!["pause", "update_admin"]
+                .into_iter()
+                .chain(always)
+                .map(String::from)
+                .collect::<Vec<_>>(),
+            available(deps.as_ref())
+        );
+
+        let mut deps = mock_dependencies(&[]);
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::Cancel { reason: None };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            expected(&["claim_refund", "reopen"]),
+            available(deps.as_ref())
+        );
+
+        let mut deps = cw20_dependencies(0);
+        let mut msg = inst_msg_with(vec![SubscriptionTerms {
+            capital: Coin::new(1000000, "cw20token"),
+            ..lp_terms()
+        }]);
+        msg.capital_cw20 = Some(Addr::unchecked("cw20token"));
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            expected(&[
+                "cancel",
+                "accept_terms",
+                "commit_capital",
+                "receive",
+                "update_terms",
+                "update_subscription",
+                "extend_due_date",
+            ]),
+            available(deps.as_ref())
+        );
+    }
+
+    #[test]
+    fn permissions() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let permissions = |deps: Deps, address: &str| -> Permissions {
+            let msg = QueryMsg::GetPermissions {
+                address: Addr::unchecked(address),
+            };
+            from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap()
+        };
+
+        // the gp can call but no longer cancel committed capital
+        assert_eq!(
+            Permissions {
+                can_cancel: false,
+                can_accept_terms: false,
+                can_commit: false,
+                can_call: true,
+                can_extend_due_date: true,
+                can_withdraw_expired: false,
+                can_update_terms: false,
+                can_reopen: false,
+                can_claim_refund: false,
+                can_pause: false,
+                can_emergency_withdraw: false,
+                can_update_admin: false,
+            },
+            permissions(deps.as_ref(), "creator")
+        );
+        assert_eq!(
+            Permissions {
+                can_cancel: true,
+                can_accept_terms: false,
+                can_commit: false,
+                can_call: true,
+                can_extend_due_date: true,
+                can_withdraw_expired: false,
+                can_update_terms: false,
+                can_reopen: false,
+                can_claim_refund: false,
+                can_pause: true,
+                can_emergency_withdraw: false,
+                can_update_admin: true,
+            },
+            permissions(deps.as_ref(), "tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000")
+        );
+        assert_eq!(
+            Permissions {
+                can_cancel: false,
+                can_accept_terms: false,
+                can_commit: false,
+                can_call: false,
+                can_extend_due_date: false,
+                can_withdraw_expired: false,
+                can_update_terms: false,
+                can_reopen: false,
+                can_claim_refund: false,
+                can_pause: false,
+                can_emergency_withdraw: false,
+                can_update_admin: false,
+            },
+            permissions(deps.as_ref(), "stranger")
+        );
+
+        // once part of the capital is drawn the rest can not be called in one go
+        let mut state = config_read(&deps.storage).load().unwrap();
+        state.drawn = Uint128::new(400000);
+        config(&mut deps.storage).save(&state).unwrap();
+        assert!(!permissions(deps.as_ref(), "creator").can_call);
+    }
+
+    #[test]
+    fn instantiate_committed() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let msg = InstantiateMsg {
+            initial_status: Some(Status::CapitalCommitted),
+            ..inst_msg()
+        };
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSubscriptions {}).unwrap();
+        let subscriptions: Vec<Subscription> = from_binary(&res).unwrap();
+        assert_eq!(Status::CapitalCommitted, subscriptions[0].status);
+        assert_eq!(Coin::new(1000000, "cfigure"), subscriptions[0].committed);
+        assert!(subscriptions[0].accepted);
+
+        // the capital was never held, so cancelling refunds nothing
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::Cancel { reason: None };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCommitted {}).unwrap();
+        let committed: Coin = from_binary(&res).unwrap();
+        assert_eq!(Coin::new(0, "cfigure"), committed);
+    }
+
+    #[test]
+    fn instantiate_committed_call() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let msg = InstantiateMsg {
+            initial_status: Some(Status::CapitalCommitted),
+            ..inst_msg()
+        };
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // the capital was never held, so the call only issues the shares and settles at once
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert!(bank_sends(&res).is_empty());
+        assert_eq!(
+            vec![
+                SubMsg::new(mint_marker_supply(10, "fund-coin").unwrap()),
+                SubMsg::new(
+                    withdraw_coins(
+                        "fund-coin",
+                        10,
+                        "fund-coin",
+                        Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+                    )
+                    .unwrap()
+                ),
+            ],
+            res.messages
+        );
+        assert!(res.attributes.contains(&attr("status", "capital_called")));
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
+        let status: Status = from_binary(&res).unwrap();
+        assert_eq!(Status::CapitalCalled, status);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetHistory {}).unwrap();
+        let history: Vec<TransitionRecord> = from_binary(&res).unwrap();
+        assert_eq!(Status::CapitalCalled, history.last().unwrap().status);
+    }
+
+    #[test]
+    fn instantiate_called() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let msg = InstantiateMsg {
+            initial_status: Some(Status::CapitalCalled),
+            ..inst_msg()
+        };
+        let err = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::WrongStatus {
+                current: Status::CapitalCalled,
+                expected: Status::CapitalCommitted,
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn status_transitions() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+
+        let statuses = [
+            Status::PendingCapital,
+            Status::CapitalCommitted,
+            Status::CapitalCalled,
+            Status::Cancelled,
+            Status::ClosePending,
+        ];
+        let legal = [
+            (Status::PendingCapital, Status::PendingCapital),
+            (Status::PendingCapital, Status::CapitalCommitted),
+            (Status::PendingCapital, Status::Cancelled),
+            (Status::CapitalCommitted, Status::CapitalCommitted),
+            (Status::CapitalCommitted, Status::CapitalCalled),
+            (Status::CapitalCommitted, Status::Cancelled),
+            (Status::CapitalCommitted, Status::ClosePending),
+            (Status::ClosePending, Status::ClosePending),
+            (Status::ClosePending, Status::CapitalCommitted),
+            (Status::ClosePending, Status::CapitalCalled),
+            (Status::ClosePending, Status::Cancelled),
+            (Status::Cancelled, Status::PendingCapital),
+        ];
+        for from in statuses.iter() {
+            for to in statuses.iter() {
+                let mut state = State {
+                    status: from.clone(),
+                    ..state.clone()
+                };
+                let res = state.transition(to.clone());
+                if legal.contains(&(from.clone(), to.clone())) {
+                    assert_eq!(Ok(()), res, "{} to {}", from, to);
+                    assert_eq!(to, &state.status);
+                } else {
+                    assert_eq!(
+                        Err(ContractError::InvalidTransition {
+                            from: from.clone(),
+                            to: to.clone(),
+                        }),
+                        res
+                    );
+                    assert_eq!(from, &state.status);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn timeline() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), env_at(1580000000), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), env_at(1590000000), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetTimeline {}).unwrap();
+        let timeline: Vec<Milestone> = from_binary(&res).unwrap();
+        assert_eq!(
+            vec![
+                Milestone {
+                    name: "instantiated".into(),
+                    reached: true,
+                    timestamp: Some(Timestamp::from_seconds(1580000000)),
+                },
+                Milestone {
+                    name: "committed".into(),
+                    reached: true,
+                    timestamp: Some(Timestamp::from_seconds(1590000000)),
+                },
+                Milestone {
+                    name: "called".into(),
+                    reached: false,
+                    timestamp: None,
+                },
+            ],
+            timeline
+        );
+
+        // a cancelled call ends with cancellation instead
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::Cancel { reason: None };
+        let _res = execute(deps.as_mut(), env_at(1590000001), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetTimeline {}).unwrap();
+        let timeline: Vec<Milestone> = from_binary(&res).unwrap();
+        assert_eq!(
+            Milestone {
+                name: "cancelled".into(),
+                reached: true,
+                timestamp: Some(Timestamp::from_seconds(1590000001)),
+            },
+            timeline[2]
+        );
+    }
+
+    #[test]
+    fn transition_times() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        let state = |deps: Deps| -> State {
+            from_binary(&query(deps, mock_env(), QueryMsg::GetState {}).unwrap()).unwrap()
+        };
+        assert_eq!(None, state(deps.as_ref()).committed_at);
+
+        // a partial commitment does not commit the capital yet
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(400000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), env_at(1590000000), info, msg).unwrap();
+        assert_eq!(None, state(deps.as_ref()).committed_at);
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(600000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), env_at(1590000001), info, msg).unwrap();
+        let committed = state(deps.as_ref());
+        assert_eq!(
+            Some(Timestamp::from_seconds(1590000001)),
+            committed.committed_at
+        );
+        assert_eq!(None, committed.called_at);
+        assert_eq!(None, committed.cancelled_at);
+
+        // the call happens once its capital send is confirmed
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let _res = execute(deps.as_mut(), env_at(1590000002), info, msg).unwrap();
+        assert_eq!(None, state(deps.as_ref()).called_at);
+        let _res = reply(deps.as_mut(), env_at(1590000002), capital_reply()).unwrap();
+        let called = state(deps.as_ref());
+        assert_eq!(Some(Timestamp::from_seconds(1590000002)), called.called_at);
+        assert_eq!(None, called.cancelled_at);
+
+        // cancelling records its own time
+        let mut deps = mock_dependencies(&[]);
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::Cancel { reason: None };
+        let _res = execute(deps.as_mut(), env_at(1590000003), info, msg).unwrap();
+        assert_eq!(
+            Some(Timestamp::from_seconds(1590000003)),
+            state(deps.as_ref()).cancelled_at
+        );
+    }
+
+    #[test]
+    fn single_coin_funds() {
+        assert_eq!(
+            ContractError::NoFundsProvided {},
+            require_single_coin(&[], "cfigure").unwrap_err()
+        );
+
+        let funds = coins(1000000, "cfigure");
+        assert_eq!(&funds[0], require_single_coin(&funds, "cfigure").unwrap());
+
+        assert_eq!(
+            ContractError::WrongDenom {
+                got: "nhash".into(),
+                expected: "cfigure".into()
+            },
+            require_single_coin(&coins(1000000, "nhash"), "cfigure").unwrap_err()
+        );
+
+        // the extra coin is named, even when it comes first
+        let funds = vec![Coin::new(1, "nhash"), Coin::new(1000000, "cfigure")];
+        assert_eq!(
+            ContractError::ExtraFunds {
+                denom: "nhash".into()
+            },
+            require_single_coin(&funds, "cfigure").unwrap_err()
+        );
+        let funds = vec![Coin::new(1, "cfigure"), Coin::new(2, "cfigure")];
+        assert_eq!(
+            ContractError::ExtraFunds {
+                denom: "cfigure".into()
+            },
+            require_single_coin(&funds, "cfigure").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn allowed_capital_denoms() {
+        let mut deps = mock_dependencies(&[]);
+
+        // the capital denom itself must be allowed
+        let msg = InstantiateMsg {
+            allowed_capital_denoms: vec!["usdf".into()],
+            ..inst_msg()
+        };
+        let info = mock_info("creator", &[]);
+        let err = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::DenomNotAllowed {
+                denom: "cfigure".into()
+            },
+            err
+        );
+
+        let msg = InstantiateMsg {
+            allowed_capital_denoms: vec!["cfigure".into(), "usdf".into()],
+            ..inst_msg()
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        // deposits outside the allowlist are rejected before the capital is matched
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "nhash"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::DenomNotAllowed {
+                denom: "nhash".into()
+            },
+            err
+        );
+
+        // allowed denoms still have to match the subscription
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "usdf"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::WrongDenom {
+                got: "usdf".into(),
+                expected: "cfigure".into(),
+            },
+            err
+        );
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    }
+
+    #[test]
+    fn call_capital_overflow() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let huge = |lp: &str| SubscriptionTerms {
+            lp_capital_source: Addr::unchecked(lp),
+            capital: Coin::new(1000000, "cfigure"),
+            shares: Coin::new(u128::MAX, "fund-coin"),
+            shares_recipient: None,
+        };
+        let msg = inst_msg_with(vec![huge("lp1"), huge("lp2")]);
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        for lp in ["lp1", "lp2"] {
+            let info = mock_info(lp, &coins(1000000, "cfigure"));
+            let _res = execute(
+                deps.as_mut(),
+                mock_env(),
+                info,
+                HandleMsg::CommitCapital {
+                    idempotency_key: None,
+                },
+            )
+            .unwrap();
+        }
+
+        // the total shares do not fit, which fails cleanly rather than panicking
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::Overflow(_)));
+    }
+
+    #[test]
+    fn invalid_denoms() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = inst_msg_with(vec![SubscriptionTerms {
+            capital: Coin::new(1000000, "c figure"),
+            ..lp_terms()
+        }]);
+        let err = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg);
+        assert_eq!(
+            ContractError::InvalidDenom {
+                denom: "c figure".into()
+            },
+            err.unwrap_err()
+        );
+
+        let msg = inst_msg_with(vec![SubscriptionTerms {
+            shares: Coin::new(10, "1fund-coin"),
+            ..lp_terms()
+        }]);
+        let err = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg);
+        assert_eq!(
+            ContractError::InvalidDenom {
+                denom: "1fund-coin".into()
+            },
+            err.unwrap_err()
+        );
+    }
+
+    #[test]
+    fn invalid_coins() {
+        let mut deps = mock_dependencies(&[]);
+
+        // zero capital would be trivially committed
+        let msg = inst_msg_with(vec![SubscriptionTerms {
+            capital: Coin::new(0, "cfigure"),
+            ..lp_terms()
+        }]);
+        let err = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg);
+        assert_eq!(
+            ContractError::InvalidCoin {
+                coin: Coin::new(0, "cfigure")
+            },
+            err.unwrap_err()
+        );
+
+        let msg = inst_msg_with(vec![SubscriptionTerms {
+            shares: Coin::new(10, ""),
+            ..lp_terms()
+        }]);
+        let err = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg);
+        assert_eq!(
+            ContractError::InvalidCoin {
+                coin: Coin::new(10, "")
+            },
+            err.unwrap_err()
+        );
+
+        let msg = inst_msg_with(vec![SubscriptionTerms {
+            shares: Coin::new(10, "cfigure"),
+            ..lp_terms()
+        }]);
+        let err = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg);
+        assert_eq!(
+            ContractError::DuplicateDenom {
+                denom: "cfigure".into()
+            },
+            err.unwrap_err()
+        );
+    }
+
+    #[test]
+    fn invalid_subscriptions() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = inst_msg_with(vec![]);
+        let err = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg);
+        assert_eq!(ContractError::NoSubscriptions {}, err.unwrap_err());
+
+        let msg = inst_msg_with(vec![lp_terms(), lp_terms()]);
+        let err = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg);
+        assert_eq!(
+            ContractError::DuplicateSubscription {
+                lp_capital_source: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7")
+            },
+            err.unwrap_err()
+        );
+
+        let msg = inst_msg_with(vec![
+            lp_terms(),
+            SubscriptionTerms {
+                lp_capital_source: Addr::unchecked("lp2"),
+                capital: Coin::new(500000, "nhash"),
+                shares: Coin::new(5, "fund-coin"),
+                shares_recipient: None,
+            },
+        ]);
+        let err = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg);
+        assert_eq!(
+            ContractError::MixedDenoms {
+                denom: "nhash".into(),
+                expected: "cfigure".into(),
+            },
+            err.unwrap_err()
+        );
+    }
+
+    #[test]
+    fn instantiate_with_funds() {
+        let mut deps = mock_dependencies(&[]);
+
+        // capital attached by the gp must cover the first subscription in full
+        let info = mock_info("creator", &coins(400000, "cfigure"));
+        let err = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap_err();
+        assert_eq!(
+            ContractError::WrongAmount {
+                got: Coin::new(400000, "cfigure"),
+                expected: Coin::new(1000000, "cfigure"),
+            },
+            err
+        );
+
+        // and is checked like any other deposit
+        let info = mock_info("creator", &coins(1000000, "nhash"));
+        let err = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap_err();
+        assert_eq!(
+            ContractError::WrongDenom {
+                got: "nhash".into(),
+                expected: "cfigure".into()
+            },
+            err
+        );
+
+        // capital committed off chain leaves nothing to attach
+        let info = mock_info("creator", &coins(1000000, "cfigure"));
+        let msg = InstantiateMsg {
+            initial_status: Some(Status::CapitalCommitted),
+            ..inst_msg()
+        };
+        let err = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::ExtraFunds {
+                denom: "cfigure".into()
+            },
+            err
+        );
+
+        // matching capital commits at once, refunding the excess to the gp
+        let info = mock_info("creator", &coins(1000001, "cfigure"));
+        let res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        assert_eq!(
+            vec![("creator".to_string(), coins(1, "cfigure"))],
+            bank_sends(&res)
+        );
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(Status::CapitalCommitted, state.status);
+        assert_eq!(Status::CapitalCommitted, state.subscriptions[0].status);
+        assert_eq!(
+            Coin::new(1000000, "cfigure"),
+            state.subscriptions[0].committed
+        );
+        assert_eq!(
+            Some(Addr::unchecked("creator")),
+            state.subscriptions[0].committed_from
+        );
+        assert!(!state.subscriptions[0].accepted);
+        assert_eq!(Some(mock_env().block.time), state.committed_at);
+
+        // a cancel refunds the gp who put the capital up
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+        let info = mock_info("creator", &coins(1000000, "cfigure"));
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::Cancel { reason: None };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![("creator".to_string(), coins(1000000, "cfigure"))],
+            bank_sends(&res)
+        );
+
+        // without funds it starts out pending as before
+        let mut deps = mock_dependencies(&[]);
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(Status::PendingCapital, state.status);
+    }
+
+    #[test]
+    fn invalid_roles() {
+        let mut deps = mock_dependencies(&[]);
+
+        let msg = InstantiateMsg {
+            admin: Addr::unchecked("creator"),
+            ..inst_msg()
+        };
+        let err = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg);
+        assert_eq!(
+            ContractError::DuplicateRole {
+                address: Addr::unchecked("creator")
+            },
+            err.unwrap_err()
+        );
+
+        let msg = inst_msg_with(vec![SubscriptionTerms {
+            lp_capital_source: Addr::unchecked("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000"),
+            ..lp_terms()
+        }]);
+        let err = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg);
+        assert_eq!(
+            ContractError::DuplicateRole {
+                address: Addr::unchecked("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000")
+            },
+            err.unwrap_err()
+        );
+
+        let msg = inst_msg_with(vec![SubscriptionTerms {
+            lp_capital_source: Addr::unchecked("x"),
+            ..lp_terms()
+        }]);
+        let err = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg);
+        assert!(matches!(err.unwrap_err(), ContractError::Std(_)));
+
+        // the contract itself can hold no role, as the admin or anywhere else
+        let contract = Addr::unchecked(MOCK_CONTRACT_ADDR);
+        let msgs = vec![
+            InstantiateMsg {
+                admin: contract.clone(),
+                ..inst_msg()
+            },
+            inst_msg_with(vec![SubscriptionTerms {
+                lp_capital_source: contract.clone(),
+                ..lp_terms()
+            }]),
+            InstantiateMsg {
+                fee_bps: Some(100),
+                fee_recipient: Some(contract.clone()),
+                ..inst_msg()
+            },
+            InstantiateMsg {
+                authorized_closers: vec![contract.clone()],
+                ..inst_msg()
+            },
+        ];
+        for msg in msgs {
+            let err = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg);
+            assert_eq!(
+                ContractError::ContractAddress {
+                    address: contract.clone()
+                },
+                err.unwrap_err()
+            );
+        }
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::UpdateAdmin {
+            new_admin: contract.clone(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(ContractError::ContractAddress { address: contract }, err);
+    }
+
+    #[test]
+    fn invalid_due_date() {
+        let mut deps = mock_dependencies(&[]);
+        let info = mock_info("creator", &[]);
+
+        let msg = InstantiateMsg {
+            due_date_time: Some("2022-01-01T00:00:00Z".into()),
+            ..inst_msg()
+        };
+        let err = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::InvalidDueDate {
+                due_date_time: "2022-01-01T00:00:00Z".into()
+            },
+            err
+        );
+
+        // nor can one be later than a timestamp holds
+        let info = mock_info("creator", &[]);
+        let msg = InstantiateMsg {
+            due_date_time: Some("99999999999".into()),
+            ..inst_msg()
+        };
+        let err = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::InvalidDueDate {
+                due_date_time: "99999999999".into()
+            },
+            err
+        );
+
+        // valid due dates are stored parsed
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetState {}).unwrap();
+        let state: State = from_binary(&res).unwrap();
+        assert_eq!(
+            Some(Timestamp::from_seconds(1600000000)),
+            state.due_date_time
+        );
+
+        // and extending to one is refused the same way
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::ExtendDueDate {
+            new_due_date_time: "99999999999".into(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::InvalidDueDate {
+                due_date_time: "99999999999".into()
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn call_capital_after_due_date() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // gp can not call capital once the due date has passed
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let err = execute(deps.as_mut(), env_at(1600000001), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::DeadlinePassed {
+                due_date_time: "1600000000".into()
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn cancel_after_due_date() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        // strangers can not cancel before the due date
+        let info = mock_info("stranger", &[]);
+        let msg = HandleMsg::Cancel { reason: None };
+        let err = execute(deps.as_mut(), env_at(1600000000), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("stranger")
+            },
+            err
+        );
+
+        // but anyone can cancel once it has passed
+        let info = mock_info("stranger", &[]);
+        let msg = HandleMsg::Cancel { reason: None };
+        let _res = execute(deps.as_mut(), env_at(1600000001), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
+        let status: Status = from_binary(&res).unwrap();
+        assert_eq!(Status::Cancelled, status);
+    }
+
+    #[test]
+    fn withdraw_expired() {
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        // nothing to withdraw until capital is committed
+        let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &[]);
+        let msg = HandleMsg::WithdrawExpired {};
+        let err = execute(deps.as_mut(), env_at(1600000001), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::WrongStatus {
+                current: Status::PendingCapital,
+                expected: Status::CapitalCommitted,
+            },
+            err
+        );
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // the lp can not withdraw before the due date
+        let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &[]);
+        let msg = HandleMsg::WithdrawExpired {};
+        let err = execute(deps.as_mut(), env_at(1600000000), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::NotPastDue {
+                due_date_time: "1600000000".into()
+            },
+            err
+        );
+
+        // and only lps can withdraw
+        let info = mock_info("stranger", &[]);
+        let msg = HandleMsg::WithdrawExpired {};
+        let err = execute(deps.as_mut(), env_at(1600000001), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("stranger")
+            },
+            err
+        );
+
+        // once it has passed the capital is refunded
+        let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &[]);
+        let msg = HandleMsg::WithdrawExpired {};
+        let res = execute(deps.as_mut(), env_at(1600000001), info, msg).unwrap();
+        assert_eq!(
+            vec![(
+                "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7".to_string(),
+                coins(1000000, "cfigure")
+            )],
+            bank_sends(&res)
+        );
+        assert_eq!(
+            vec![
+                attr("action", "withdraw_expired"),
+                attr("prior_status", "capital_committed"),
+                attr("status", "cancelled"),
+                attr("sender", "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+                attr("transfer_type", "refund"),
+                attr("refund", "1000000cfigure"),
+            ],
+            res.attributes
+        );
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
+        let status: Status = from_binary(&res).unwrap();
+        assert_eq!(Status::Cancelled, status);
+    }
+
+    #[test]
+    fn withdraw_expired_after_draw() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::DrawCapital {
+            capital_amount: Uint128::new(400000),
+            min_capital_out: None,
+            max_shares_out: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        deps.querier
+            .base
+            .update_balance(MOCK_CONTRACT_ADDR, coins(600000, "cfigure"));
+
+        // only the capital left undrawn goes back to the lp
+        let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &[]);
+        let msg = HandleMsg::WithdrawExpired {};
+        let res = execute(deps.as_mut(), env_at(1600000001), info, msg).unwrap();
+        assert_eq!(
+            vec![(
+                "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7".to_string(),
+                coins(600000, "cfigure")
+            )],
+            bank_sends(&res)
+        );
+
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(Status::Cancelled, state.status);
+        assert_eq!(Coin::new(0, "cfigure"), state.subscriptions[0].committed);
+    }
+
+    #[test]
+    fn update_terms_keeps_the_commit_fee_apart() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let msg = InstantiateMsg {
+            fee_recipient: Some(Addr::unchecked("platform")),
+            commit_fee: Some(Coin::new(100, "nhash")),
+            ..inst_msg()
+        };
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // the capital can not move onto the commit fee denom, as instantiate would not allow it
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::UpdateTerms {
+            updates: vec![TermsUpdate {
+                lp_capital_source: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+                capital: Some(Coin::new(1000000, "nhash")),
+                shares: None,
+            }],
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::InvalidCommitFee {
+                fee: Coin::new(100, "nhash")
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn update_terms_keeps_cw20_capital() {
+        let mut deps = mock_dependencies(&[]);
+
+        let mut msg = inst_msg_with(vec![SubscriptionTerms {
+            capital: Coin::new(1000000, "cw20token"),
+            ..lp_terms()
+        }]);
+        msg.capital_cw20 = Some(Addr::unchecked("cw20token"));
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::UpdateTerms {
+            updates: vec![TermsUpdate {
+                lp_capital_source: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+                capital: Some(Coin::new(1000000, "cfigure")),
+                shares: None,
+            }],
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::WrongDenom {
+                got: "cfigure".into(),
+                expected: "cw20token".into()
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn update_terms() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        let redenominate = || HandleMsg::UpdateTerms {
+            updates: vec![TermsUpdate {
+                lp_capital_source: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+                capital: Some(Coin::new(1000000, "usdf")),
+                shares: None,
+            }],
+        };
+
+        // only the admin can update terms
+        let info = mock_info("creator", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, redenominate()).unwrap_err();
+        assert_eq!(
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("creator")
+            },
+            err
+        );
+
+        // shares can not take the capital denom
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::UpdateTerms {
+            updates: vec![TermsUpdate {
+                lp_capital_source: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+                capital: None,
+                shares: Some(Coin::new(10, "cfigure")),
+            }],
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::DuplicateDenom {
+                denom: "cfigure".into()
+            },
+            err
+        );
+
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, redenominate()).unwrap();
+        assert_eq!(
+            vec![
+                attr("action", "update_terms"),
+                attr("prior_status", "pending_capital"),
+                attr("status", "pending_capital"),
+                attr("sender", "tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000"),
+                attr(
+                    "lp_capital_source",
+                    "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"
+                ),
+                attr("prior_capital", "1000000cfigure"),
+                attr("capital", "1000000usdf"),
+            ],
+            res.attributes
+        );
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCommitted {}).unwrap();
+        let committed: Coin = from_binary(&res).unwrap();
+        assert_eq!(Coin::new(0, "usdf"), committed);
+
+        // the lp has to accept the new terms before committing in the new denom
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "usdf"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::TermsNotAccepted {
+                lp_capital_source: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7")
+            },
+            err
+        );
+        accept_terms(deps.as_mut());
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "usdf"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // and the terms are fixed from then on
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, redenominate()).unwrap_err();
+        assert_eq!(
+            ContractError::WrongStatus {
+                current: Status::CapitalCommitted,
+                expected: Status::PendingCapital,
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn extend_due_date() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        // only the gp or admin can extend the due date
+        let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &[]);
+        let msg = HandleMsg::ExtendDueDate {
+            new_due_date_time: "1700000000".into(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7")
+            },
+            err
+        );
+
+        // the due date can only move later
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::ExtendDueDate {
+            new_due_date_time: "1500000000".into(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::DueDateNotExtended {
+                due_date_time: "1600000000".into(),
+                new_due_date_time: "1500000000".into(),
+            },
+            err
+        );
+
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::ExtendDueDate {
+            new_due_date_time: "1700000000".into(),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![
+                attr("action", "extend_due_date"),
+                attr("prior_status", "pending_capital"),
+                attr("status", "pending_capital"),
+                attr("sender", "tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000"),
+                attr("prior_due_date_time", "1600000000"),
+                attr("due_date_time", "1700000000"),
+            ],
+            res.attributes
+        );
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetState {}).unwrap();
+        let state: State = from_binary(&res).unwrap();
+        assert_eq!(
+            Some(Timestamp::from_seconds(1700000000)),
+            state.due_date_time
+        );
+    }
+
+    #[test]
+    fn pause() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        // only the admin can pause
+        let info = mock_info("creator", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, HandleMsg::Pause {}).unwrap_err();
+        assert_eq!(
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("creator")
+            },
+            err
+        );
+
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, HandleMsg::Pause {}).unwrap();
+
+        // lp can not commit while paused
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(ContractError::Paused {}, err);
+
+        // queries still work while paused
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
+        let status: Status = from_binary(&res).unwrap();
+        assert_eq!(Status::PendingCapital, status);
+
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, HandleMsg::Unpause {}).unwrap();
+
+        // lp can commit once unpaused
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(1000000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    }
+
+    #[test]
+    fn query_detailed_status() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        let detailed_status = |deps: Deps| -> DetailedStatus {
+            from_binary(&query(deps, mock_env(), QueryMsg::GetDetailedStatus {}).unwrap()).unwrap()
+        };
+        assert_eq!(
+            DetailedStatus {
+                status: Status::PendingCapital,
+                committed_ratio: 0,
+                fully_committed: false,
+            },
+            detailed_status(deps.as_ref())
+        );
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(500000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            DetailedStatus {
+                status: Status::PendingCapital,
+                committed_ratio: 50,
+                fully_committed: false,
+            },
+            detailed_status(deps.as_ref())
+        );
+
+        let info = mock_info(
+            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+            &coins(500000, "cfigure"),
+        );
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            DetailedStatus {
+                status: Status::CapitalCommitted,
+                committed_ratio: 100,
+                fully_committed: true,
+            },
+            detailed_status(deps.as_ref())
+        );
     }
 
     #[test]
-    fn cancel() {
-        let mut deps = mock_dependencies(&coins(2, "token"));
+    fn validate_capital_marker() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let mut capital_marker: Marker = from_binary(&bin).unwrap();
+        capital_marker.denom = "cfigure".into();
+        capital_marker.marker_type = MarkerType::Restricted;
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_markers(vec![capital_marker.clone()]);
 
         let info = mock_info("creator", &[]);
         let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
 
-        // lp can commit capital
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::ValidateCapitalMarker {},
+        )
+        .unwrap();
+        let marker: CapitalMarker = from_binary(&res).unwrap();
+        assert_eq!(
+            CapitalMarker {
+                denom: "cfigure".into(),
+                address: capital_marker.address,
+                status: MarkerStatus::Active,
+                restricted: true,
+                valid: true,
+            },
+            marker
+        );
+    }
+
+    #[test]
+    fn is_expired() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+
+        let res = query(deps.as_ref(), env_at(1599999900), QueryMsg::IsExpired {}).unwrap();
+        let expiry: Expiry = from_binary(&res).unwrap();
+        assert_eq!(
+            Expiry {
+                expired: false,
+                seconds_remaining: Some(100),
+            },
+            expiry
+        );
+
+        let res = query(deps.as_ref(), env_at(1600000100), QueryMsg::IsExpired {}).unwrap();
+        let expiry: Expiry = from_binary(&res).unwrap();
+        assert_eq!(
+            Expiry {
+                expired: true,
+                seconds_remaining: Some(-100),
+            },
+            expiry
+        );
+    }
+
+    #[test]
+    fn query_before_instantiate() {
+        let deps = mock_dependencies(&[]);
+
+        for msg in [
+            QueryMsg::GetStatus {},
+            QueryMsg::GetHeldFunds {},
+            QueryMsg::GetVersion {},
+        ] {
+            let err = query(deps.as_ref(), mock_env(), msg).unwrap_err();
+            assert_eq!(StdError::generic_err("Not instantiated"), err);
+        }
+    }
+
+    #[test]
+    fn query_commit_requirement() {
+        let mut deps = mock_dependencies(&[]);
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        let requirement = |deps: Deps, address: &str| {
+            let msg = QueryMsg::GetCommitRequirement {
+                address: Addr::unchecked(address),
+            };
+            let res = query(deps, mock_env(), msg)?;
+            from_binary::<Vec<Coin>>(&res)
+        };
+
+        assert!(requirement(deps.as_ref(), "stranger").is_err());
+        assert_eq!(
+            coins(1000000, "cfigure"),
+            requirement(deps.as_ref(), "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7").unwrap()
+        );
+
+        // only what is left is required, and nothing once committed
+        for (deposit, required) in [(400000, coins(600000, "cfigure")), (600000, vec![])] {
+            let info = mock_info(
+                "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+                &coins(deposit, "cfigure"),
+            );
+            let msg = HandleMsg::CommitCapital {
+                idempotency_key: None,
+            };
+            let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+            assert_eq!(
+                required,
+                requirement(deps.as_ref(), "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7").unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn execute_data() {
+        let bin = must_read_binary_file("testdata/marker.json");
+        let expected_marker: Marker = from_binary(&bin).unwrap();
+        let mut deps = mock_dependencies(&coins(1000000, "cfigure"));
+        deps.querier.with_markers(vec![expected_marker]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        // each execution returns the status and commitments it left behind
+        for (deposit, status) in [
+            (400000, Status::PendingCapital),
+            (600000, Status::CapitalCommitted),
+        ] {
+            let info = mock_info(
+                "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
+                &coins(deposit, "cfigure"),
+            );
+            let msg = HandleMsg::CommitCapital {
+                idempotency_key: None,
+            };
+            let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+            let data: ExecuteResponse = from_binary(&res.data.unwrap()).unwrap();
+            let state = config_read(&deps.storage).load().unwrap();
+            assert_eq!(
+                ExecuteResponse {
+                    status,
+                    committed: state.committed().unwrap(),
+                },
+                data
+            );
+        }
+
+        // a call only reports called once its reply confirms it
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::CallCapital { allocations: None };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let data: ExecuteResponse = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!(Status::CapitalCommitted, data.status);
+        let res = reply(deps.as_mut(), mock_env(), capital_reply()).unwrap();
+        let data: ExecuteResponse = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!(
+            ExecuteResponse {
+                status: Status::CapitalCalled,
+                committed: Coin::new(1000000, "cfigure"),
+            },
+            data
+        );
+    }
+
+    #[test]
+    fn commit_fee() {
+        let mut deps = mock_dependencies(&[]);
+
+        // the fee needs somewhere to go and a denom of its own
+        for (fee, fee_recipient) in [
+            (Coin::new(50, "nhash"), None),
+            (Coin::new(50, "cfigure"), Some(Addr::unchecked("platform"))),
+            (
+                Coin::new(50, "fund-coin"),
+                Some(Addr::unchecked("platform")),
+            ),
+        ] {
+            let msg = InstantiateMsg {
+                commit_fee: Some(fee.clone()),
+                fee_recipient,
+                ..inst_msg()
+            };
+            let err = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg);
+            assert_eq!(ContractError::InvalidCommitFee { fee }, err.unwrap_err());
+        }
+
+        let msg = InstantiateMsg {
+            commit_fee: Some(Coin::new(50, "nhash")),
+            fee_recipient: Some(Addr::unchecked("platform")),
+            ..inst_msg()
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
+
+        // the requirement covers the fee on top of the capital
+        let msg = QueryMsg::GetCommitRequirement {
+            address: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let required: Vec<Coin> = from_binary(&res).unwrap();
+        assert_eq!(
+            vec![Coin::new(1000000, "cfigure"), Coin::new(50, "nhash")],
+            required
+        );
+
+        // the capital alone, or with too little fee, is turned away
+        for funds in [
+            coins(1000000, "cfigure"),
+            vec![Coin::new(1000000, "cfigure"), Coin::new(49, "nhash")],
+        ] {
+            let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &funds);
+            let err = execute(
+                deps.as_mut(),
+                mock_env(),
+                info,
+                HandleMsg::CommitCapital {
+                    idempotency_key: None,
+                },
+            );
+            assert_eq!(
+                ContractError::CommitFeeRequired {
+                    fee: Coin::new(50, "nhash")
+                },
+                err.unwrap_err()
+            );
+        }
+
+        // with the fee it is sent straight on, and only the capital is committed
         let info = mock_info(
             "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
-            &coins(1000000, "cfigure"),
+            &[Coin::new(1000000, "cfigure"), Coin::new(50, "nhash")],
         );
-        let msg = HandleMsg::CommitCapital {};
-        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            HandleMsg::CommitCapital {
+                idempotency_key: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            vec![("platform".to_string(), coins(50, "nhash"))],
+            bank_sends(&res)
+        );
+        assert!(res.attributes.contains(&attr("commit_fee", "50nhash")));
+        assert!(res
+            .attributes
+            .contains(&attr("transfer_type", "commit_fee")));
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(
+            Coin::new(1000000, "cfigure"),
+            state.subscriptions[0].committed
+        );
+    }
 
-        // gp can cancel capital call
+    #[test]
+    fn query_raw_config_key() {
+        let mut deps = mock_dependencies(&[]);
         let info = mock_info("creator", &[]);
-        let msg = HandleMsg::Cancel {};
-        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
 
-        // should be in pending capital state
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
-        let status: Status = from_binary(&res).unwrap();
-        assert_eq!(Status::Cancelled, status);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRawConfigKey {}).unwrap();
+        let key: Binary = from_binary(&res).unwrap();
+        assert_eq!(&[0, 6], &key.as_slice()[..2]);
+        assert_eq!(CONFIG_KEY, &key.as_slice()[2..]);
 
-        // should send stable coin back to lp
-        let (to_address, amount) = _res
-            .messages
-            .iter()
-            .find_map(|sub_msg| match &sub_msg.msg {
-                CosmosMsg::Bank(bank) => match bank {
-                    BankMsg::Send { to_address, amount } => Some((to_address, amount)),
-                    _ => None,
-                },
-                _ => None,
-            })
-            .unwrap();
-        assert_eq!("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", to_address);
-        assert_eq!(1000000, u128::from(amount[0].amount));
-        assert_eq!("cfigure", amount[0].denom);
+        // a raw read at the key is the state as json
+        let raw = deps.storage.get(key.as_slice()).unwrap();
+        let state: State = from_slice(&raw).unwrap();
+        assert_eq!(config_read(&deps.storage).load().unwrap(), state);
     }
 
     #[test]
-    fn call_capital() {
-        // Create a mock querier with our expected marker.
+    fn query_parties() {
         let bin = must_read_binary_file("testdata/marker.json");
         let expected_marker: Marker = from_binary(&bin).unwrap();
         let mut deps = mock_dependencies(&[]);
-        deps.querier.with_markers(vec![expected_marker.clone()]);
+        deps.querier.with_markers(vec![expected_marker]);
 
         let info = mock_info("creator", &[]);
-        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        let msg = InstantiateMsg {
+            fee_bps: Some(100),
+            fee_recipient: Some(Addr::unchecked("fees")),
+            commit_fee: Some(Coin::new(100, "nhash")),
+            ..inst_msg_with(vec![
+                lp_terms(),
+                SubscriptionTerms {
+                    lp_capital_source: Addr::unchecked("lp2"),
+                    capital: Coin::new(500000, "cfigure"),
+                    shares: Coin::new(5, "fund-coin"),
+                    shares_recipient: Some(Addr::unchecked("custodian")),
+                },
+            ])
+        };
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetParties {}).unwrap();
+        let parties: Parties = from_binary(&res).unwrap();
+        assert_eq!(
+            Parties {
+                recipients: vec![
+                    Addr::unchecked("tp18vmzryrvwaeykmdtu6cfrz5sau3dhc5c73ms0u"),
+                    Addr::unchecked("fees"),
+                    Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+                    Addr::unchecked("lp2"),
+                    Addr::unchecked("custodian"),
+                ],
+                denoms: vec!["cfigure".into(), "fund-coin".into(), "nhash".into()],
+            },
+            parties
+        );
+    }
+
+    #[test]
+    fn query_terms() {
+        let mut deps = mock_dependencies(&[]);
+
+        // the id must be set and bounded
+        let info = mock_info("creator", &[]);
+        let msg = InstantiateMsg {
+            id: "".into(),
+            ..inst_msg()
+        };
+        let err = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::InvalidId {
+                id: "".into(),
+                max: 64
+            },
+            err
+        );
+
+        let info = mock_info("creator", &[]);
+        let msg = InstantiateMsg {
+            capital_decimals: Some(6),
+            shares_decimals: Some(0),
+            ..inst_msg()
+        };
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetTerms {}).unwrap();
+        let terms: Terms = from_binary(&res).unwrap();
+        assert_eq!(
+            Terms {
+                id: "capital-call-1".into(),
+                gp: Addr::unchecked("creator"),
+                admin: Addr::unchecked("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000"),
+                subscriptions: vec![lp_terms()],
+                due_date_time: Some(Timestamp::from_seconds(1600000000)),
+                capital_decimals: Some(6),
+                shares_decimals: Some(0),
+            },
+            terms
+        );
+    }
+
+    #[test]
+    fn emergency_withdraw_far_out() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let msg = InstantiateMsg {
+            emergency_grace_period: Some(u64::MAX / 2),
+            ..inst_msg()
+        };
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // a grace period past the latest representable time never ends, rather than panicking
+        let permissions = query_permissions(
+            deps.as_ref(),
+            env_at(1700000000),
+            Addr::unchecked("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000"),
+        )
+        .unwrap();
+        assert!(!permissions.can_emergency_withdraw);
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::EmergencyWithdraw {
+            recipient: Addr::unchecked("recovery"),
+        };
+        let err = execute(deps.as_mut(), env_at(1700000000), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::GracePeriodNotOver {
+                ends: "18446744073".into()
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn emergency_withdraw() {
+        let mut deps = mock_dependencies(&coins(400000, "cfigure"));
+
+        let info = mock_info("creator", &[]);
+        let msg = InstantiateMsg {
+            emergency_grace_period: Some(86400),
+            ..inst_msg()
+        };
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        accept_terms(deps.as_mut());
 
-        // lp can commit capital
         let info = mock_info(
             "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
-            &coins(1000000, "cfigure"),
+            &coins(400000, "cfigure"),
         );
-        let msg = HandleMsg::CommitCapital {};
-        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let msg = HandleMsg::CommitCapital {
+            idempotency_key: None,
+        };
+        let _res = execute(deps.as_mut(), env_at(1590000000), info, msg).unwrap();
 
-        // gp can call capital
-        let info = mock_info("creator", &vec![]);
-        let msg = HandleMsg::CallCapital {};
+        // the grace period starts at the due date
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::EmergencyWithdraw {
+            recipient: Addr::unchecked("recovery"),
+        };
+        let err = execute(deps.as_mut(), env_at(1600086400), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::GracePeriodNotOver {
+                ends: "1600086400".into()
+            },
+            err
+        );
+
+        // only the admin can sweep
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::EmergencyWithdraw {
+            recipient: Addr::unchecked("recovery"),
+        };
+        let err = execute(deps.as_mut(), env_at(1600086401), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("creator")
+            },
+            err
+        );
+
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::EmergencyWithdraw {
+            recipient: Addr::unchecked("recovery"),
+        };
+        let res = execute(deps.as_mut(), env_at(1600086401), info, msg).unwrap();
+        assert_eq!(
+            vec![("recovery".to_string(), coins(400000, "cfigure"))],
+            bank_sends(&res)
+        );
+
+        // nothing is left for the lp to claim
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSubscriptions {}).unwrap();
+        let subscriptions: Vec<Subscription> = from_binary(&res).unwrap();
+        assert_eq!(Status::Cancelled, subscriptions[0].status);
+        assert_eq!(Coin::new(0, "cfigure"), subscriptions[0].committed);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
+        let status: Status = from_binary(&res).unwrap();
+        assert_eq!(Status::Cancelled, status);
+    }
+
+    #[test]
+    fn attach_document() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+
+        let hash = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::AttachDocument {
+            hash: hash.into(),
+            uri: Some("https://example.com/agreement.pdf".into()),
+        };
         let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-        let mint = _res
-            .messages
-            .iter()
-            .find_map(|sub_msg| match &sub_msg.msg {
-                CosmosMsg::Custom(custom) => match custom {
-                    ProvenanceMsg {
-                        route: _,
-                        params,
-                        version: _,
-                    } => match params {
-                        ProvenanceMsgParams::Marker(params) => match params {
-                            MarkerMsgParams::MintMarkerSupply { coin } => Some(coin),
-                            _ => None,
-                        },
-                        _ => None,
-                    },
-                },
-                _ => None,
-            })
-            .unwrap();
-        assert_eq!(10, u128::from(mint.amount));
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetDocuments {}).unwrap();
+        let refs: Vec<DocumentRef> = from_binary(&res).unwrap();
+        assert_eq!(
+            vec![DocumentRef {
+                hash: hash.into(),
+                uri: Some("https://example.com/agreement.pdf".into()),
+                timestamp: mock_env().block.time,
+                sender: Addr::unchecked("creator"),
+            }],
+            refs
+        );
 
-        let (withdraw_coin, withdraw_recipient) = _res
-            .messages
-            .iter()
-            .find_map(|sub_msg| match &sub_msg.msg {
-                CosmosMsg::Custom(custom) => match custom {
-                    ProvenanceMsg {
-                        route: _,
-                        params,
-                        version: _,
-                    } => match params {
-                        ProvenanceMsgParams::Marker(params) => match params {
-                            MarkerMsgParams::WithdrawCoins {
-                                marker_denom: _,
-                                coin,
-                                recipient,
-                            } => Some((coin, recipient)),
-                            _ => None,
-                        },
-                        _ => None,
-                    },
-                },
-                _ => None,
+        // anything but a hex sha-256 digest is turned away
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::AttachDocument {
+            hash: "not-a-hash".into(),
+            uri: None,
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::InvalidHash {
+                hash: "not-a-hash".into()
+            },
+            err
+        );
+
+        // lps can not attach documents
+        let info = mock_info("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7", &[]);
+        let msg = HandleMsg::AttachDocument {
+            hash: hash.into(),
+            uri: None,
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7")
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn update_admin() {
+        let mut deps = mock_dependencies(&[]);
+
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+
+        // only the admin can hand over the role
+        let info = mock_info("creator", &[]);
+        let msg = HandleMsg::UpdateAdmin {
+            new_admin: Addr::unchecked("creator"),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("creator")
+            },
+            err
+        );
+
+        // and not to the gp or an lp
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::UpdateAdmin {
+            new_admin: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+        };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        assert_eq!(
+            ContractError::DuplicateRole {
+                address: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7")
+            },
+            err
+        );
+
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let msg = HandleMsg::UpdateAdmin {
+            new_admin: Addr::unchecked("new_admin"),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![
+                attr("action", "update_admin"),
+                attr("prior_status", "pending_capital"),
+                attr("status", "pending_capital"),
+                attr("sender", "tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000"),
+                attr("prior_admin", "tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000"),
+                attr("admin", "new_admin"),
+            ],
+            res.attributes
+        );
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAdmin {}).unwrap();
+        let admin: Addr = from_binary(&res).unwrap();
+        assert_eq!(Addr::unchecked("new_admin"), admin);
+
+        // the prior admin lost the role
+        let info = mock_info("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, HandleMsg::Pause {}).unwrap_err();
+        assert_eq!(
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000")
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn migrate_legacy_state() {
+        let mut deps = mock_dependencies(&[]);
+
+        // contracts instantiated before versioning stored an unversioned state
+        singleton(&mut deps.storage, CONFIG_KEY)
+            .save(&StateV0 {
+                status: Status::CapitalCommitted,
+                gp: Addr::unchecked("creator"),
+                lp_capital_source: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+                admin: Addr::unchecked("tp1apnhcu9x5cz2l8hhgnj0hg7ez53jah7hcan000"),
+                capital: Coin::new(1000000, "cfigure"),
+                shares: Coin::new(10, "fund-coin"),
             })
             .unwrap();
-        assert_eq!(10, u128::from(withdraw_coin.amount));
+
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+        // the contract info is recorded from then on
+        let info = get_contract_version(&deps.storage).unwrap();
+        assert_eq!(CONTRACT_NAME, info.contract);
+        assert_eq!(CONTRACT_VERSION, info.version);
+
+        // state should load in its current shape with the version bumped
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(STATE_VERSION, state.version);
+        assert_eq!(Status::CapitalCommitted, state.status);
         assert_eq!(
-            "tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7",
-            withdraw_recipient.to_string()
+            vec![Subscription {
+                lp_capital_source: Addr::unchecked("tp18lysxk7sueunnspju4dar34vlv98a7kyyfkqs7"),
+                status: Status::CapitalCommitted,
+                capital: Coin::new(1000000, "cfigure"),
+                committed: Coin::new(1000000, "cfigure"),
+                shares: Coin::new(10, "fund-coin"),
+                shares_recipient: None,
+                committed_from: None,
+                accepted: true,
+                close_confirmed: false,
+            }],
+            state.subscriptions
         );
+    }
 
-        let (to_address, amount) = _res
-            .messages
-            .iter()
-            .find_map(|sub_msg| match &sub_msg.msg {
-                CosmosMsg::Bank(bank) => match bank {
-                    BankMsg::Send { to_address, amount } => Some((to_address, amount)),
-                    _ => None,
-                },
-                _ => None,
+    #[test]
+    fn migrate_newer_state() {
+        let mut deps = mock_dependencies(&[]);
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+        accept_terms(deps.as_mut());
+
+        // code must not downgrade state written by a newer version
+        config(&mut deps.storage)
+            .update(|mut state| -> StdResult<_> {
+                state.version = STATE_VERSION + 1;
+                Ok(state)
             })
             .unwrap();
-        assert_eq!("tp18vmzryrvwaeykmdtu6cfrz5sau3dhc5c73ms0u", to_address);
-        assert_eq!(1000000, u128::from(amount[0].amount));
-        assert_eq!("cfigure", amount[0].denom);
 
-        // should be in capital called state
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetStatus {}).unwrap();
-        let status: Status = from_binary(&res).unwrap();
-        assert_eq!(Status::CapitalCalled, status);
+        let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+        assert_eq!(
+            ContractError::UnsupportedVersion {
+                stored: STATE_VERSION + 1,
+                supported: STATE_VERSION,
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn contract_version() {
+        let mut deps = mock_dependencies(&[]);
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, inst_msg()).unwrap();
+
+        let info = get_contract_version(&deps.storage).unwrap();
+        assert_eq!(
+            "crates.io:marketpalace-capital-call-contract",
+            info.contract
+        );
+        assert_eq!(env!("CARGO_PKG_VERSION"), info.version);
+
+        // only this contract can be migrated to it
+        set_contract_version(&mut deps.storage, "crates.io:other-contract", "1.0.0").unwrap();
+        let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+        assert_eq!(
+            ContractError::WrongContract {
+                contract: "crates.io:other-contract".into()
+            },
+            err
+        );
     }
 }
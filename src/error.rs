@@ -1,13 +1,197 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{Addr, Coin, OverflowError, StdError};
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+use crate::state::{Role, Status};
+
+#[derive(Error, Debug, PartialEq)]
 pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
-    #[error("Unauthorized")]
-    Unauthorized {},
-    // Add any other custom errors you like here.
-    // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
+    #[error("Not instantiated")]
+    NotInstantiated {},
+
+    #[error("Unauthorized: {sender}")]
+    Unauthorized { sender: Addr },
+
+    #[error("Not subscribed: {address}")]
+    NotSubscribed { address: Addr },
+
+    #[error("Wrong status: {current}, expected {expected}")]
+    WrongStatus { current: Status, expected: Status },
+
+    #[error("Invalid transition: {from} to {to}")]
+    InvalidTransition { from: Status, to: Status },
+
+    #[error("Already finished: {status}")]
+    AlreadyFinished { status: Status },
+
+    #[error("Wrong denom: got {got}, expected {expected}")]
+    WrongDenom { got: String, expected: String },
+
+    #[error("Wrong shares: got {got}, expected at most {expected}")]
+    WrongShares { got: Coin, expected: Coin },
+
+    #[error("Wrong amount: got {got}, expected {expected}")]
+    WrongAmount { got: Coin, expected: Coin },
+
+    #[error("Extra funds: {denom}, only the capital may be attached")]
+    ExtraFunds { denom: String },
+
+    #[error("Unexpected funds: {denom}, only CommitCapital and CommitCapitalFrom take funds")]
+    UnexpectedFunds { denom: String },
+
+    #[error("Invalid idempotency key: {key:?}, expected 1 to {max} bytes")]
+    InvalidIdempotencyKey { key: String, max: usize },
+
+    #[error("Duplicate commit: {key:?} was already used by this lp")]
+    DuplicateCommit { key: String },
+
+    #[error("Cw20 capital: {token}, commit it by sending the tokens instead")]
+    Cw20Capital { token: Addr },
+
+    #[error("Below minimum: {deposit}, deposits must be at least {min_commitment}")]
+    BelowMinimum { deposit: Coin, min_commitment: Coin },
+
+    #[error("Terms not accepted: {lp_capital_source}")]
+    TermsNotAccepted { lp_capital_source: Addr },
+
+    #[error("Terms already accepted: {lp_capital_source}")]
+    AlreadyAccepted { lp_capital_source: Addr },
+
+    #[error("Close already confirmed: {lp_capital_source}")]
+    AlreadyConfirmed { lp_capital_source: Addr },
+
+    #[error("No funds provided")]
+    NoFundsProvided {},
+
+    #[error("Invalid coin: {coin}, a non-zero amount and denom are required")]
+    InvalidCoin { coin: Coin },
+
+    #[error(
+        "Invalid denom: {denom:?}, expected a letter followed by 2 to 127 of [a-zA-Z0-9/:._-]"
+    )]
+    InvalidDenom { denom: String },
+
+    #[error("Duplicate denom: {denom}, capital and shares must differ")]
+    DuplicateDenom { denom: String },
+
+    #[error("Contract address: {address}, the contract can not hold a role itself")]
+    ContractAddress { address: Addr },
+
+    #[error("Duplicate role: {address}, admin, gp and lps must all differ")]
+    DuplicateRole { address: Addr },
+
+    #[error("Already committed: {committed}, terms can only change before any capital")]
+    AlreadyCommitted { committed: Coin },
+
+    #[error(
+        "Partially committed: {committed}, capital can only be pulled for a whole subscription"
+    )]
+    PartiallyCommitted { committed: Coin },
+
+    #[error("Refunds outstanding: {committed} has not been claimed")]
+    RefundsOutstanding { committed: Coin },
+
+    #[error("No refund")]
+    NoRefund {},
+
+    #[error("Invalid id: {id:?}, expected 1 to {max} bytes")]
+    InvalidId { id: String, max: usize },
+
+    #[error("No subscriptions")]
+    NoSubscriptions {},
+
+    #[error("Duplicate subscription: {lp_capital_source}")]
+    DuplicateSubscription { lp_capital_source: Addr },
+
+    #[error("Mixed denoms: {denom}, every subscription must use {expected}")]
+    MixedDenoms { denom: String, expected: String },
+
+    #[error("Denom not allowed: {denom}")]
+    DenomNotAllowed { denom: String },
+
+    #[error("Invalid due date: {due_date_time}, expected unix seconds")]
+    InvalidDueDate { due_date_time: String },
+
+    #[error("Due date not extended: {new_due_date_time} is not after {due_date_time}")]
+    DueDateNotExtended {
+        due_date_time: String,
+        new_due_date_time: String,
+    },
+
+    #[error("Deadline passed: {due_date_time}")]
+    DeadlinePassed { due_date_time: String },
+
+    #[error("Reason too long: {length} bytes, at most {max}")]
+    ReasonTooLong { length: usize, max: usize },
+
+    #[error("Invalid hash: {hash:?}, expected a hex sha-256 digest")]
+    InvalidHash { hash: String },
+
+    #[error("Uri too long: {length} bytes, at most {max}")]
+    UriTooLong { length: usize, max: usize },
+
+    #[error("Too many documents: at most {max}")]
+    TooManyDocuments { max: usize },
+
+    #[error("Not past due: {due_date_time}")]
+    NotPastDue { due_date_time: String },
+
+    #[error("Invalid fee: {fee_bps} basis points, at most 10000 with a fee recipient")]
+    InvalidFee { fee_bps: u16 },
+
+    #[error("Invalid commit fee: {fee}, needs a fee recipient and a denom other than the capital and shares")]
+    InvalidCommitFee { fee: Coin },
+
+    #[error("Commit fee required: {fee} must be attached")]
+    CommitFeeRequired { fee: Coin },
+
+    #[error("Insufficient funds: {held} held, {required} required")]
+    InsufficientFunds { held: Coin, required: Coin },
+
+    #[error("Draw exceeds committed: {capital}, only {remaining} is left to draw")]
+    DrawExceedsCommitted { capital: Coin, remaining: Coin },
+
+    #[error("Capital out too low: {capital}, at least {min} expected")]
+    CapitalOutTooLow { capital: Coin, min: Coin },
+
+    #[error("Shares out too high: {shares}, at most {max} expected")]
+    SharesOutTooHigh { shares: Coin, max: Coin },
+
+    #[error("Partially drawn: {drawn}, the rest must be drawn with DrawCapital")]
+    PartiallyDrawn { drawn: Coin },
+
+    #[error("Too soon: capital can be called from {earliest}")]
+    TooSoon { earliest: String },
+
+    #[error("Reentrancy: a call is awaiting its reply")]
+    Reentrancy {},
+
+    #[error("No grace period: emergency withdrawals need a due date and grace period")]
+    NoGracePeriod {},
+
+    #[error("Grace period not over: ends {ends}")]
+    GracePeriodNotOver { ends: String },
+
+    #[error("Role already granted: {role} to {address}")]
+    RoleAlreadyGranted { address: Addr, role: Role },
+
+    #[error("Role not granted: {role} to {address}")]
+    RoleNotGranted { address: Addr, role: Role },
+
+    #[error("Paused")]
+    Paused {},
+
+    #[error("Unknown reply: {id}")]
+    UnknownReply { id: u64 },
+
+    #[error("Wrong contract: {contract}, can only migrate from this contract")]
+    WrongContract { contract: String },
+
+    #[error("Unsupported state version: {stored}, newest supported {supported}")]
+    UnsupportedVersion { stored: u16, supported: u16 },
 }
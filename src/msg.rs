@@ -1,12 +1,99 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, Coin};
+use cosmwasm_std::{Addr, Coin, Timestamp, Uint128};
+use cw20::Cw20ReceiveMsg;
+use provwasm_std::MarkerStatus;
 
+use crate::state::{DocumentRef, FeeBasis, Role, Status, Subscription, TransitionRecord};
+
+// Capital the instantiating gp attaches, checked like a CommitCapital deposit, commits the first
+// subscription in full at once. The gp stands in for that lp, so the commit does not wait for
+// its AcceptTerms and the subscription stays unaccepted. Its refunds go back to the gp
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
-    pub lp_capital_source: Addr,
+    // stable id of the deal for off chain systems, 1 to 64 bytes
+    pub id: String,
     pub admin: Addr,
+    pub subscriptions: Vec<SubscriptionTerms>,
+    // cw20 token the capital is in rather than a native coin, the capital denom must then be its
+    // address. Lps commit by sending it with a ReceiveMsg, so it can not take a commit fee
+    pub capital_cw20: Option<Addr>,
+    // where called capital is sent, the shares marker when absent
+    pub capital_recipient: Option<Addr>,
+    // unix timestamp in seconds after which capital can no longer be called
+    pub due_date_time: Option<String>,
+    // attribute name, bound to this contract, added to each lp when its shares are issued
+    pub settlement_attribute: Option<String>,
+    // capital denoms accepted for compliance, any denom matching the subscriptions when empty
+    #[serde(default)]
+    pub allowed_capital_denoms: Vec<String>,
+    // basis points of the called capital sent to fee_recipient, at most 10000
+    pub fee_bps: Option<u16>,
+    // Shares takes fee_bps from each lp's shares instead, Capital when absent
+    pub fee_basis: Option<FeeBasis>,
+    pub fee_recipient: Option<Addr>,
+    // flat fee attached alongside each CommitCapital and sent straight to fee_recipient, in a
+    // denom other than the capital and shares
+    pub commit_fee: Option<Coin>,
+    // delegates allowed to call capital on the gp's behalf, capital still settles as usual
+    #[serde(default)]
+    pub authorized_closers: Vec<Addr>,
+    // contracts notified with an ObserverMsg when capital is called or the call is cancelled. A
+    // notification that fails is ignored rather than failing the call
+    #[serde(default)]
+    pub observers: Vec<Addr>,
+    // registry contract the deal registers with at instantiate and reports each status change to,
+    // ignoring a report that fails
+    pub registry: Option<Addr>,
+    // whether cancelling refunds every lp at once, true when absent. Otherwise each lp claims
+    // its own refund with ClaimRefund
+    pub auto_refund: Option<bool>,
+    // PendingCapital when absent. CapitalCommitted bootstraps a legacy deal whose capital was
+    // committed off chain, so nothing is refunded if it is cancelled and calling it only issues
+    // the shares
+    pub initial_status: Option<Status>,
+    // smallest deposit CommitCapital accepts in the capital denom, unless it completes the
+    // subscription
+    pub min_commitment: Option<Coin>,
+    // seconds past the due date after which the admin may EmergencyWithdraw, never when absent
+    pub emergency_grace_period: Option<u64>,
+    // seconds that must pass after capital is first committed before it can be called
+    pub min_commit_duration: Option<u64>,
+    // decimal places of the capital and shares denoms, only for clients to display amounts
+    pub capital_decimals: Option<u8>,
+    pub shares_decimals: Option<u8>,
+}
+
+// Every subscription must use the same capital and shares denoms
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SubscriptionTerms {
+    pub lp_capital_source: Addr,
+    pub capital: Coin,
+    pub shares: Coin,
+    // where the lp custodies its shares, the lp itself when absent
+    pub shares_recipient: Option<Addr>,
+}
+
+// Shares to issue a committed lp, when fewer than agreed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Allocation {
+    pub lp_capital_source: Addr,
+    pub shares: Uint128,
+}
+
+// Replacement terms for a subscription, fields left out are kept
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TermsUpdate {
+    pub lp_capital_source: Addr,
+    pub capital: Option<Coin>,
+    pub shares: Option<Coin>,
+}
+
+// Json value of the settlement attribute
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SettlementAttribute {
+    pub gp: Addr,
     pub capital: Coin,
     pub shares: Coin,
 }
@@ -18,9 +105,168 @@ pub struct MigrateMsg {}
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum HandleMsg {
-    Cancel {},
-    CommitCapital {},
-    CallCapital {},
+    // Every handler emits `action`, `prior_status`, `status` and `sender` attributes, and a
+    // `transfer_type` of `refund`, `commit_fee`, `fee`, `settlement` or `sweep` for each bank
+    // send it makes, in the order the sends go out. Only CommitCapital and CommitCapitalFrom
+    // accept attached funds, every other handler fails with UnexpectedFunds.
+    //
+    // Messages always go out in the same order: refunds, then shares minted and withdrawn to
+    // each lp and, with a Shares fee basis, to the fee recipient, then settlement attributes,
+    // then a capital fee, then the capital settlement, which a completed call confirms in reply.
+    // Observer and registry notifications come last.
+    //
+    // Cancel additionally emits the `reason` when given and a `refund` for each lp that had
+    // committed capital. The reason is kept in state and limited to 256 bytes
+    Cancel {
+        reason: Option<String>,
+    },
+    // AcceptTerms records that the sending lp agrees to its subscription, which CommitCapital
+    // requires
+    AcceptTerms {},
+    // CommitCapital additionally emits `capital` (the deposit accepted) and `committed` (the lp's
+    // running total), the `commit_fee` when one is configured, and a `refund` of anything
+    // deposited beyond the capital still required. A retried commit carrying an idempotency_key the
+    // lp already committed with is rejected, so its funds go back rather than counting twice. The
+    // most recent 64 keys of each lp are remembered
+    CommitCapital {
+        idempotency_key: Option<String>,
+    },
+    // CommitCapitalFrom commits the sending lp's whole capital by a marker transfer from an owner
+    // that allowed it, rather than attached funds, though any commit fee is still attached.
+    // Refunds go back to the owner. It additionally emits the `owner`, `capital` and `committed`.
+    // Cw20 capital can not be committed this way
+    CommitCapitalFrom {
+        owner: Addr,
+    },
+    // Receive is the cw20 hook a capital token calls when an lp sends capital to the contract,
+    // carrying a ReceiveMsg. Refunds and settlement of cw20 capital are cw20 transfers
+    Receive(Cw20ReceiveMsg),
+    // CallCapital settles every committed subscription and refunds any partial commitments.
+    // It additionally emits `capital` and `shares` (the totals actually called), the `fee` taken
+    // from that capital when one is configured, and a `refund` for each lp not settled in full.
+    // The status only becomes CapitalCalled once the capital send is confirmed in reply, and
+    // every handler fails with Reentrancy until then
+    CallCapital {
+        allocations: Option<Vec<Allocation>>,
+    },
+    // DrawCapital draws part of the committed capital at the agreed price, issuing each lp its
+    // share of the matching shares, and keeps the status CapitalCommitted until all of it is drawn.
+    // The first draw refunds any partial commitments, and once capital is drawn the rest can only
+    // be drawn the same way. Emits the same attributes as CallCapital. The draw fails, changing
+    // nothing, if the capital sent on net of the fee is below min_capital_out or the shares issued
+    // exceed max_shares_out.
+    //
+    // Formerly PartialCall, which payloads may still use
+    #[serde(alias = "partial_call")]
+    DrawCapital {
+        capital_amount: Uint128,
+        min_capital_out: Option<Uint128>,
+        max_shares_out: Option<Uint128>,
+    },
+    // ProposeClose lets anyone who may call capital propose calling all of it, moving to
+    // ClosePending until every committed lp confirms with ConfirmClose, once each. The last
+    // confirmation settles the call like CallCapital and emits the same attributes. Until then
+    // AbortClose, from the proposer's side or any committed lp, returns the call to
+    // CapitalCommitted
+    ProposeClose {},
+    ConfirmClose {},
+    AbortClose {},
+    // ClaimRefund returns the sending lp's capital from a call cancelled without auto_refund, or
+    // whatever a cancel could not refund. Like a cancel it never sends more than is held, and
+    // the rest can be claimed later. It additionally emits the `refund`
+    ClaimRefund {},
+    // Reopen lets the admin return a cancelled call to PendingCapital once every lp has been
+    // refunded, so capital has to be committed again from scratch
+    Reopen {},
+    // WithdrawExpired lets an lp unwind committed capital that was not called by its due date,
+    // refunding every lp like Cancel
+    WithdrawExpired {},
+    // ExtendDueDate additionally emits `prior_due_date_time` and `due_date_time`
+    ExtendDueDate {
+        new_due_date_time: String,
+    },
+    // UpdateTerms lets the admin change subscription terms before any capital is committed, e.g.
+    // to redenominate every subscription at once, after which the updated lps must accept them
+    // again. It additionally emits `lp_capital_source`
+    // followed by `prior_capital` and `capital` and/or `prior_shares` and `shares` for each update
+    UpdateTerms {
+        updates: Vec<TermsUpdate>,
+    },
+    // UpdateSharesAmount lets the admin reprice a subscription's shares while capital is
+    // committed, e.g. when the fund's NAV shifts before the call, keeping the shares denom and
+    // the capital. It additionally emits `lp_capital_source`, `prior_shares` and `shares`
+    UpdateSharesAmount {
+        lp_capital_source: Addr,
+        shares: Coin,
+    },
+    // UpdateSubscription lets the admin correct a subscription's lp address while capital is
+    // pending and before that lp commits any, after which the new lp must accept the terms. It
+    // additionally emits `prior_lp_capital_source` and `lp_capital_source`
+    UpdateSubscription {
+        lp_capital_source: Addr,
+        new_lp_capital_source: Addr,
+    },
+    // EmergencyWithdraw lets the admin recover a call abandoned past its due date and grace
+    // period, sending every balance held to the recipient and cancelling it without refunds. It
+    // works while paused and additionally emits the `recipient` and a `transfer_type` of `sweep`
+    EmergencyWithdraw {
+        recipient: Addr,
+    },
+    // UpdateAdmin hands the admin role to a new address, which must differ from the gp and every
+    // lp. It works while paused and additionally emits `prior_admin` and `admin`
+    UpdateAdmin {
+        new_admin: Addr,
+    },
+    // AttachDocument lets the gp or admin anchor an off chain document, such as a subscription
+    // agreement, by its hex sha-256 hash. At most 32 are kept, uris are limited to 256 bytes. It
+    // additionally emits the `hash`
+    AttachDocument {
+        hash: String,
+        uri: Option<String>,
+    },
+    // GrantRole and RevokeRole let the admin manage roles granted on top of the admin and
+    // authorized closers, additionally emitting the `address` and `role`
+    GrantRole {
+        address: Addr,
+        role: Role,
+    },
+    RevokeRole {
+        address: Addr,
+        role: Role,
+    },
+    // Pause halts every other handler except UpdateAdmin until Unpause, both are admin only
+    Pause {},
+    Unpause {},
+}
+
+// Message an lp sends along with cw20 capital
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    // CommitCapital commits the tokens sent exactly like an attached deposit
+    CommitCapital { idempotency_key: Option<String> },
+}
+
+// Message each observer is executed with once the call settles or is cancelled
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ObserverMsg {
+    StatusChanged { id: String, status: Status },
+}
+
+// Message the registry is executed with, on instantiate and on every status change after
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistryMsg {
+    Register {
+        id: String,
+        contract_address: Addr,
+        status: Status,
+    },
+    UpdateStatus {
+        id: String,
+        status: Status,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -28,4 +274,180 @@ pub enum HandleMsg {
 pub enum QueryMsg {
     // GetStatus returns the current status as a json-encoded number
     GetStatus {},
+    // GetDetailedStatus returns the status with the percentage of the subscribed capital
+    // committed so far
+    GetDetailedStatus {},
+    // GetAdmin returns the current admin address
+    GetAdmin {},
+    // ValidateCapitalMarker returns the marker behind the capital denom, which is only valid when
+    // it is an active restricted marker. Fails when the denom has no marker at all
+    ValidateCapitalMarker {},
+    // IsExpired returns whether the due date has passed at the current block time
+    IsExpired {},
+    // GetTerms returns the id and the terms agreed at instantiate, as updated since
+    GetTerms {},
+    // GetCommitted returns the capital committed so far by all lps as a json-encoded coin
+    GetCommitted {},
+    // GetRemaining returns the capital still to be committed by pending lps as a json-encoded coin
+    GetRemaining {},
+    // GetCommitRequirement returns the coins the lp must attach to CommitCapital to complete its
+    // subscription, with any commit fee, none once nothing more can be committed
+    GetCommitRequirement {
+        address: Addr,
+    },
+    // GetSubscriptions returns every subscription with its own status and committed capital
+    GetSubscriptions {},
+    // GetState returns the entire contract state as json
+    GetState {},
+    // GetHeldFunds returns every coin the contract actually holds, to check against what it has
+    // recorded as committed
+    GetHeldFunds {},
+    // GetRawConfigKey returns the storage key the state is kept under as base64, for raw queries.
+    // It is CONFIG_KEY behind its two byte length
+    GetRawConfigKey {},
+    // GetVersion returns the version of the stored state shape as a json-encoded number
+    GetVersion {},
+    // GetHistory returns every successful execution in order, with the status it left behind
+    GetHistory {},
+    // GetDocuments returns every attached document in order
+    GetDocuments {},
+    // GetPermissions returns which handlers the address could currently execute
+    GetPermissions {
+        address: Addr,
+    },
+    // GetAvailableActions returns the snake_case name of every HandleMsg the current status and
+    // pause accept from someone, whoever the sender. Time limits such as the due date are not
+    // taken into account
+    GetAvailableActions {},
+    // SimulateCall returns every transfer CallCapital would make with the given allocations, in
+    // the order it would make them, without making them
+    SimulateCall {
+        allocations: Option<Vec<Allocation>>,
+    },
+    // GetCloseMessageCount returns how many messages CallCapital without allocations would send
+    // now, observer and registry notifications included, as a json-encoded number, for estimating
+    // its fees
+    GetCloseMessageCount {},
+    // GetSummary returns the status, terms, committed capital and transition times in one go
+    GetSummary {},
+    // GetParties returns every address the contract may send funds to and every denom it moves,
+    // for screening before execution, commit fee denoms included. EmergencyWithdraw recipients
+    // are only known when it runs
+    GetParties {},
+    // GetAuditTrail returns the history, documents and parties together, for compliance review
+    GetAuditTrail {},
+    // GetTimeline returns the instantiated and committed milestones followed by either called or
+    // cancelled, whichever the call ends with
+    GetTimeline {},
+}
+
+// Data of every successful execution, the status and capital committed it left behind
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ExecuteResponse {
+    pub status: Status,
+    pub committed: Coin,
+}
+
+// Response to GetPermissions, pause covers unpausing too
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Permissions {
+    pub can_cancel: bool,
+    pub can_accept_terms: bool,
+    pub can_commit: bool,
+    pub can_call: bool,
+    pub can_extend_due_date: bool,
+    pub can_withdraw_expired: bool,
+    pub can_update_terms: bool,
+    pub can_reopen: bool,
+    pub can_claim_refund: bool,
+    pub can_pause: bool,
+    pub can_emergency_withdraw: bool,
+    pub can_update_admin: bool,
+}
+
+// Response to GetDetailedStatus
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DetailedStatus {
+    pub status: Status,
+    pub committed_ratio: u8,
+    pub fully_committed: bool,
+}
+
+// Response to ValidateCapitalMarker
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CapitalMarker {
+    pub denom: String,
+    pub address: Addr,
+    pub status: MarkerStatus,
+    pub restricted: bool,
+    pub valid: bool,
+}
+
+// Response to IsExpired, the seconds remaining are negative once expired and unset without a
+// due date
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Expiry {
+    pub expired: bool,
+    pub seconds_remaining: Option<i64>,
+}
+
+// Response to GetTerms
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Terms {
+    pub id: String,
+    pub gp: Addr,
+    pub admin: Addr,
+    pub subscriptions: Vec<SubscriptionTerms>,
+    pub due_date_time: Option<Timestamp>,
+    pub capital_decimals: Option<u8>,
+    pub shares_decimals: Option<u8>,
+}
+
+// Response to GetParties, each address and denom listed once
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Parties {
+    pub recipients: Vec<Addr>,
+    pub denoms: Vec<String>,
+}
+
+// Response to GetAuditTrail
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AuditTrail {
+    pub id: String,
+    pub gp: Addr,
+    pub admin: Addr,
+    pub parties: Parties,
+    pub history: Vec<TransitionRecord>,
+    pub documents: Vec<DocumentRef>,
+}
+
+// Response to GetSummary
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Summary {
+    pub status: Status,
+    pub gp: Addr,
+    pub admin: Addr,
+    pub subscriptions: Vec<Subscription>,
+    pub committed: Coin,
+    pub due_date_time: Option<Timestamp>,
+    pub committed_at: Option<Timestamp>,
+    pub called_at: Option<Timestamp>,
+    pub cancelled_at: Option<Timestamp>,
+}
+
+// Response item of GetTimeline, the timestamp is unset until the milestone is reached
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Milestone {
+    pub name: String,
+    pub reached: bool,
+    pub timestamp: Option<Timestamp>,
+}
+
+// Response item of SimulateCall. The transfer type is `shares` for shares issued to an lp,
+// otherwise the `transfer_type` the matching send is tagged with
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Transfer {
+    pub transfer_type: String,
+    pub recipient: Addr,
+    pub coin: Coin,
 }
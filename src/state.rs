@@ -1,19 +1,209 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
-use cosmwasm_std::{Addr, Coin, Storage};
-use cosmwasm_storage::{singleton, singleton_read, ReadonlySingleton, Singleton};
+use cosmwasm_std::{Addr, Coin, StdResult, Storage, Timestamp, Uint128};
+use cosmwasm_storage::{
+    singleton, singleton_read, to_length_prefixed, ReadonlySingleton, Singleton,
+};
 
+use crate::error::ContractError;
+
+// The State is stored as json under this key, length prefixed like every singleton, so raw
+// queries read it at 0x0006 followed by "config". The key never changes, new fields are only
+// ever added to the json
 pub static CONFIG_KEY: &[u8] = b"config";
+pub static HISTORY_KEY: &[u8] = b"history";
+pub static PENDING_TRANSITION_KEY: &[u8] = b"pending_transition";
+pub static DOCUMENTS_KEY: &[u8] = b"documents";
+pub static COMMIT_KEYS_KEY: &[u8] = b"commit_keys";
+
+// Version of the State shape written by this code, migrate upgrades anything older
+pub const STATE_VERSION: u16 = 1;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct State {
+    pub version: u16,
+    // stable id off chain systems key the deal by, empty for contracts migrated from before ids
+    pub id: String,
     pub status: Status,
     pub gp: Addr,
-    pub lp_capital_source: Addr,
     pub admin: Addr,
+    pub subscriptions: Vec<Subscription>,
+    // cw20 token the capital is in, its address being the capital denom
+    pub capital_cw20: Option<Addr>,
+    pub capital_recipient: Option<Addr>,
+    pub due_date_time: Option<Timestamp>,
+    pub settlement_attribute: Option<String>,
+    pub allowed_capital_denoms: Vec<String>,
+    pub fee_bps: Option<u16>,
+    pub fee_basis: FeeBasis,
+    pub fee_recipient: Option<Addr>,
+    // flat fee charged on each commitment, apart from the fee on called capital
+    pub commit_fee: Option<Coin>,
+    pub authorized_closers: Vec<Addr>,
+    // roles granted since instantiate, on top of the admin and authorized closers
+    pub roles: Vec<(Addr, Role)>,
+    // contracts notified when capital is called or the call is cancelled
+    pub observers: Vec<Addr>,
+    // registry contract told of every status change
+    pub registry: Option<Addr>,
+    pub auto_refund: bool,
+    // seconds past the due date after which the admin may sweep every held balance
+    pub emergency_grace_period: Option<u64>,
+    // seconds after committed_at before capital can be called
+    pub min_commit_duration: Option<u64>,
+    // smallest deposit accepted, except for the one completing a subscription
+    pub min_commitment: Option<Coin>,
+    // decimal places clients display the capital and shares amounts with, unset when unknown
+    pub capital_decimals: Option<u8>,
+    pub shares_decimals: Option<u8>,
+    // set when instantiated already committed, the contract never held that capital
+    pub committed_off_chain: bool,
+    // capital drawn so far by partial calls
+    pub drawn: Uint128,
+    pub paused: bool,
+    // block times of each transition, unset until it happens
+    pub instantiated_at: Option<Timestamp>,
+    pub committed_at: Option<Timestamp>,
+    pub called_at: Option<Timestamp>,
+    pub cancelled_at: Option<Timestamp>,
+    pub cancel_reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Subscription {
+    pub lp_capital_source: Addr,
+    pub status: Status,
     pub capital: Coin,
+    pub committed: Coin,
     pub shares: Coin,
+    // where the lp custodies its shares, the lp itself when absent
+    pub shares_recipient: Option<Addr>,
+    // the owner the capital was pulled from by CommitCapitalFrom, refunds go back to it
+    pub committed_from: Option<Addr>,
+    // set once the lp accepts the terms above, capital can only be committed after
+    pub accepted: bool,
+    // set once the committed lp confirms the close proposed, reset whenever one is proposed
+    pub close_confirmed: bool,
+}
+
+impl State {
+    // every status change goes through here, so no handler can make an illegal one. Capital is
+    // committed then called, possibly through a close proposed and confirmed, anything not yet
+    // called can be cancelled, and a cancelled call can be reopened. Staying put is only legal
+    // before the call finishes
+    pub fn transition(&mut self, to: Status) -> Result<(), ContractError> {
+        let legal = matches!(
+            (&self.status, &to),
+            (Status::PendingCapital, Status::PendingCapital)
+                | (Status::PendingCapital, Status::CapitalCommitted)
+                | (Status::PendingCapital, Status::Cancelled)
+                | (Status::CapitalCommitted, Status::CapitalCommitted)
+                | (Status::CapitalCommitted, Status::CapitalCalled)
+                | (Status::CapitalCommitted, Status::Cancelled)
+                | (Status::CapitalCommitted, Status::ClosePending)
+                | (Status::ClosePending, Status::ClosePending)
+                | (Status::ClosePending, Status::CapitalCommitted)
+                | (Status::ClosePending, Status::CapitalCalled)
+                | (Status::ClosePending, Status::Cancelled)
+                | (Status::Cancelled, Status::PendingCapital)
+        );
+        if !legal {
+            return Err(ContractError::InvalidTransition {
+                from: self.status.clone(),
+                to,
+            });
+        }
+
+        self.status = to;
+        Ok(())
+    }
+
+    // the admin and authorized closers hold their roles without being granted them
+    pub fn has_role(&self, address: &Addr, role: Role) -> bool {
+        let implicit = match role {
+            Role::Admin => address == &self.admin,
+            Role::Closer => self.authorized_closers.contains(address),
+            Role::Canceller => false,
+        };
+        implicit || self.roles.contains(&(address.clone(), role))
+    }
+
+    pub fn allows_capital_denom(&self, denom: &str) -> bool {
+        self.allowed_capital_denoms.is_empty()
+            || self
+                .allowed_capital_denoms
+                .iter()
+                .any(|allowed| allowed == denom)
+    }
+
+    pub fn subscription(&self, lp_capital_source: &Addr) -> Option<&Subscription> {
+        self.subscriptions
+            .iter()
+            .find(|sub| &sub.lp_capital_source == lp_capital_source)
+    }
+
+    pub fn shares_recipient(&self, lp_capital_source: &Addr) -> Addr {
+        self.subscription(lp_capital_source)
+            .and_then(|sub| sub.shares_recipient.clone())
+            .unwrap_or_else(|| lp_capital_source.clone())
+    }
+
+    pub fn subscription_mut(&mut self, lp_capital_source: &Addr) -> Option<&mut Subscription> {
+        self.subscriptions
+            .iter_mut()
+            .find(|sub| &sub.lp_capital_source == lp_capital_source)
+    }
+
+    // capital is committed as soon as any one subscription is fully committed
+    pub fn pending_status(&self) -> Status {
+        if self
+            .subscriptions
+            .iter()
+            .any(|sub| sub.status == Status::CapitalCommitted)
+        {
+            Status::CapitalCommitted
+        } else {
+            Status::PendingCapital
+        }
+    }
+
+    // all subscriptions share the capital and shares denoms, checked at instantiate
+    pub fn committed(&self) -> StdResult<Coin> {
+        let mut amount = Uint128::zero();
+        for sub in self.subscriptions.iter() {
+            amount = amount.checked_add(sub.committed.amount)?;
+        }
+        Ok(Coin {
+            denom: self.subscriptions[0].capital.denom.clone(),
+            amount,
+        })
+    }
+
+    // capital still outstanding from subscriptions that can be committed to
+    pub fn remaining(&self) -> StdResult<Coin> {
+        let mut amount = Uint128::zero();
+        for sub in self.subscriptions.iter() {
+            if sub.status == Status::PendingCapital {
+                amount =
+                    amount.checked_add(sub.capital.amount.saturating_sub(sub.committed.amount))?;
+            }
+        }
+        Ok(Coin {
+            denom: self.subscriptions[0].capital.denom.clone(),
+            amount,
+        })
+    }
+}
+
+impl Subscription {
+    // where refunds of committed capital go, the lp itself unless it was pulled from an owner
+    pub fn refund_recipient(&self) -> &Addr {
+        self.committed_from
+            .as_ref()
+            .unwrap_or(&self.lp_capital_source)
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -22,12 +212,203 @@ pub enum Status {
     Cancelled,
     CapitalCommitted,
     CapitalCalled,
+    ClosePending,
+}
+
+// Admins may do anything the admin can, closers call capital like the gp, and cancellers
+// cancel like the admin
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub enum Role {
+    Admin,
+    Closer,
+    Canceller,
+}
+
+// Which side of a call fee_bps is taken from, the capital sent to the gp or the shares issued to
+// the lps
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub enum FeeBasis {
+    Capital,
+    Shares,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TransitionRecord {
+    pub status: Status,
+    pub timestamp: Timestamp,
+    pub sender: Addr,
+}
+
+// An off chain document, such as a subscription agreement, anchored by its sha-256 hash
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DocumentRef {
+    pub hash: String,
+    pub uri: Option<String>,
+    pub timestamp: Timestamp,
+    pub sender: Addr,
+}
+
+// An idempotency key a lp has already committed with
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CommitKey {
+    pub lp_capital_source: Addr,
+    pub key: String,
+}
+
+// State as stored by contracts instantiated before it carried a version
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StateV0 {
+    pub status: Status,
+    pub gp: Addr,
+    pub lp_capital_source: Addr,
+    pub admin: Addr,
+    pub capital: Coin,
+    pub shares: Coin,
+}
+
+impl From<StateV0> for State {
+    fn from(state: StateV0) -> Self {
+        // unversioned contracts held a single lp that committed the full capital at once, and
+        // never asked it to accept the terms
+        let committed = match state.status {
+            Status::CapitalCommitted | Status::CapitalCalled => state.capital.clone(),
+            _ => Coin::new(0, state.capital.denom.clone()),
+        };
+
+        State {
+            version: STATE_VERSION,
+            id: String::new(),
+            status: state.status.clone(),
+            gp: state.gp,
+            admin: state.admin,
+            subscriptions: vec![Subscription {
+                lp_capital_source: state.lp_capital_source,
+                status: state.status,
+                capital: state.capital,
+                committed,
+                shares: state.shares,
+                shares_recipient: None,
+                committed_from: None,
+                accepted: true,
+                close_confirmed: false,
+            }],
+            capital_cw20: None,
+            capital_recipient: None,
+            due_date_time: None,
+            settlement_attribute: None,
+            allowed_capital_denoms: vec![],
+            fee_bps: None,
+            fee_basis: FeeBasis::Capital,
+            fee_recipient: None,
+            commit_fee: None,
+            authorized_closers: vec![],
+            roles: vec![],
+            observers: vec![],
+            registry: None,
+            auto_refund: true,
+            emergency_grace_period: None,
+            min_commit_duration: None,
+            min_commitment: None,
+            capital_decimals: None,
+            shares_decimals: None,
+            committed_off_chain: false,
+            drawn: Uint128::zero(),
+            paused: false,
+            instantiated_at: None,
+            committed_at: None,
+            called_at: None,
+            cancelled_at: None,
+            cancel_reason: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredVersion {
+    #[serde(default)]
+    version: u16,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Status::PendingCapital => "pending_capital",
+            Status::Cancelled => "cancelled",
+            Status::CapitalCommitted => "capital_committed",
+            Status::CapitalCalled => "capital_called",
+            Status::ClosePending => "close_pending",
+        })
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Role::Admin => "admin",
+            Role::Closer => "closer",
+            Role::Canceller => "canceller",
+        })
+    }
 }
 
-pub fn config(storage: &mut dyn Storage) -> Singleton<State> {
+pub fn config(storage: &mut dyn Storage) -> Singleton<'_, State> {
     singleton(storage, CONFIG_KEY)
 }
 
-pub fn config_read(storage: &dyn Storage) -> ReadonlySingleton<State> {
+pub fn config_read(storage: &dyn Storage) -> ReadonlySingleton<'_, State> {
+    singleton_read(storage, CONFIG_KEY)
+}
+
+// the key raw queries read the state at
+pub fn config_raw_key() -> Vec<u8> {
+    to_length_prefixed(CONFIG_KEY)
+}
+
+pub fn config_version(storage: &dyn Storage) -> StdResult<u16> {
+    let stored: StoredVersion = singleton_read(storage, CONFIG_KEY).load()?;
+    Ok(stored.version)
+}
+
+pub fn legacy_config_read(storage: &dyn Storage) -> ReadonlySingleton<'_, StateV0> {
     singleton_read(storage, CONFIG_KEY)
 }
+
+pub fn history(storage: &mut dyn Storage) -> Singleton<'_, Vec<TransitionRecord>> {
+    singleton(storage, HISTORY_KEY)
+}
+
+pub fn history_read(storage: &dyn Storage) -> ReadonlySingleton<'_, Vec<TransitionRecord>> {
+    singleton_read(storage, HISTORY_KEY)
+}
+
+// a transition held back until the reply confirms the submessage that settles it
+pub fn pending_transition(storage: &mut dyn Storage) -> Singleton<'_, TransitionRecord> {
+    singleton(storage, PENDING_TRANSITION_KEY)
+}
+
+pub fn pending_transition_read(storage: &dyn Storage) -> ReadonlySingleton<'_, TransitionRecord> {
+    singleton_read(storage, PENDING_TRANSITION_KEY)
+}
+
+pub fn documents(storage: &mut dyn Storage) -> Singleton<'_, Vec<DocumentRef>> {
+    singleton(storage, DOCUMENTS_KEY)
+}
+
+pub fn documents_read(storage: &dyn Storage) -> ReadonlySingleton<'_, Vec<DocumentRef>> {
+    singleton_read(storage, DOCUMENTS_KEY)
+}
+
+pub fn commit_keys(storage: &mut dyn Storage) -> Singleton<'_, Vec<CommitKey>> {
+    singleton(storage, COMMIT_KEYS_KEY)
+}
+
+pub fn commit_keys_read(storage: &dyn Storage) -> ReadonlySingleton<'_, Vec<CommitKey>> {
+    singleton_read(storage, COMMIT_KEYS_KEY)
+}
+
+// contracts migrated from before the history existed start with an empty one
+pub fn append_history(storage: &mut dyn Storage, record: TransitionRecord) -> StdResult<()> {
+    let mut records = history_read(storage).may_load()?.unwrap_or_default();
+    records.push(record);
+    history(storage).save(&records)
+}